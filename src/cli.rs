@@ -0,0 +1,304 @@
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::process::ExitCode;
+
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::Input;
+
+use rlox::{Dialect, Error, Lox};
+
+// Exit statuses follow the BSD sysexits convention.
+const EX_USAGE: u8 = 64;
+const EX_DATAERR: u8 = 65;
+const EX_NOINPUT: u8 = 66;
+const EX_SOFTWARE: u8 = 70;
+const EX_IOERR: u8 = 74;
+
+/// The status of `rlox fmt --check` when a file is not formatted, as for
+/// `diff`.
+const EX_UNFORMATTED: u8 = 1;
+
+const USAGE: &str = "\
+Usage: rlox [options] [script | -] [args...]
+       rlox [options] fmt [--check] [file... | -]
+
+Runs the REPL when no script is given. A script path of `-` reads the
+script from stdin.
+
+`fmt` reformats files in place, or stdin to stdout when given `-` or no
+files. With `--check`, files are left as they are, and the status is 1 if
+any of them is not formatted.
+
+Options:
+  -e <source>          Evaluate <source> instead of a script
+  --dialect <dialect>  `strict` (the default) to only allow bools in
+                       conditions and comparisons of like types, or
+                       `canonical` for the truthiness and equality of
+                       canonical Lox
+  --error-format <format>
+                       `human` (the default) to describe errors with the
+                       source they were found in, or `json` for a JSON
+                       object per error, one per line
+  -h, --help           Print this message";
+
+/// Settings shared by every command.
+#[derive(Default)]
+pub struct Options {
+    dialect: Dialect,
+    error_format: ErrorFormat,
+}
+
+/// How errors are written to stderr.
+#[derive(Default)]
+enum ErrorFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+impl Options {
+    fn lox(&self, args: Vec<String>) -> Lox {
+        let mut lox = Lox::with_args(args);
+        lox.set_dialect(self.dialect);
+        lox
+    }
+
+    /// Prints `errs`, found in `source` read from `file`.
+    ///
+    /// Human readable errors are colored when written to a terminal. They go
+    /// to stderr, so it is stderr that is checked, not stdout.
+    fn report(&self, errs: &[Error], file: &str, source: &str) {
+        let color = io::stderr().is_terminal();
+        for e in errs.iter() {
+            match self.error_format {
+                ErrorFormat::Human => eprint!("{}", e.diagnostic().render(file, source, color)),
+                ErrorFormat::Json => eprintln!("{}", e.diagnostic().to_json()),
+            }
+        }
+    }
+}
+
+pub enum Command {
+    Help,
+    Repl,
+    Eval { source: String, args: Vec<String> },
+    Run { path: String, args: Vec<String> },
+    Fmt { paths: Vec<String>, check: bool },
+}
+
+impl Command {
+    pub fn parse<I>(args: I) -> Result<(Self, Options), String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut args = args.into_iter();
+        let mut options = Options::default();
+
+        // Options come before the script, whose own arguments are left alone.
+        loop {
+            let Some(arg) = args.next() else {
+                return Ok((Self::Repl, options));
+            };
+
+            let command = match arg.as_str() {
+                "-h" | "--help" => Self::Help,
+                "-e" => match args.next() {
+                    Some(source) => Self::Eval {
+                        source,
+                        args: args.collect(),
+                    },
+                    None => return Err("option `-e` expects Lox source".to_string()),
+                },
+                "--dialect" => {
+                    options.dialect = match args.next().as_deref() {
+                        Some("strict") => Dialect::Strict,
+                        Some("canonical") => Dialect::Canonical,
+                        Some(dialect) => return Err(format!("unknown dialect `{dialect}`")),
+                        None => return Err("option `--dialect` expects a dialect".to_string()),
+                    };
+                    continue;
+                }
+                "--error-format" => {
+                    options.error_format = match args.next().as_deref() {
+                        Some("human") => ErrorFormat::Human,
+                        Some("json") => ErrorFormat::Json,
+                        Some(format) => return Err(format!("unknown error format `{format}`")),
+                        None => return Err("option `--error-format` expects a format".to_string()),
+                    };
+                    continue;
+                }
+                opt if opt.starts_with('-') && opt != "-" => {
+                    return Err(format!("unknown option `{opt}`"))
+                }
+                "fmt" => {
+                    let mut paths = vec![];
+                    let mut check = false;
+                    for arg in args {
+                        match arg.as_str() {
+                            "--check" => check = true,
+                            opt if opt.starts_with('-') && opt != "-" => {
+                                return Err(format!("unknown option `{opt}` for `fmt`"))
+                            }
+                            _ => paths.push(arg),
+                        }
+                    }
+                    Self::Fmt { paths, check }
+                }
+                _ => Self::Run {
+                    path: arg,
+                    args: args.collect(),
+                },
+            };
+            return Ok((command, options));
+        }
+    }
+
+    pub fn execute(self, options: &Options) -> ExitCode {
+        match self {
+            Self::Help => {
+                println!("{USAGE}");
+                ExitCode::SUCCESS
+            }
+            Self::Repl => run_repl(options),
+            Self::Eval { source, args } => run_source(options, "<eval>", source, args),
+            Self::Run { path, args } => match read_script(&path) {
+                Ok(source) => {
+                    let file = if path == "-" { "<stdin>" } else { &path };
+                    run_source(options, file, source, args)
+                }
+                Err(e) => ExitCode::from(read_error(&path, e)),
+            },
+            Self::Fmt { paths, check } => format_files(options, paths, check),
+        }
+    }
+}
+
+pub fn usage_error(message: String) -> ExitCode {
+    eprintln!("rlox: {message}\n\n{USAGE}");
+    ExitCode::from(EX_USAGE)
+}
+
+fn read_script(path: &str) -> io::Result<String> {
+    let bytes = match path {
+        "-" => {
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes)?;
+            bytes
+        }
+        _ => fs::read(path)?,
+    };
+
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+/// Reports that `path` could not be read, returning the exit status for it.
+fn read_error(path: &str, e: io::Error) -> u8 {
+    eprintln!("rlox: cannot read `{path}`: {e}");
+    match e.kind() {
+        io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied => EX_NOINPUT,
+        _ => EX_IOERR,
+    }
+}
+
+/// Formats each of `paths` in place, or stdin to stdout for `-`, or only
+/// checks that they are formatted if `check` is set.
+fn format_files(options: &Options, mut paths: Vec<String>, check: bool) -> ExitCode {
+    if paths.is_empty() {
+        paths.push("-".to_string());
+    }
+
+    // The most serious failure decides the status.
+    let mut status = 0;
+    for path in paths.iter() {
+        let source = match read_script(path) {
+            Ok(source) => source,
+            Err(e) => {
+                status = status.max(read_error(path, e));
+                continue;
+            }
+        };
+        let file = if path == "-" { "<stdin>" } else { path };
+        let formatted = match rlox::format(&source, file) {
+            Ok(formatted) => formatted,
+            Err(errs) => {
+                options.report(&errs, file, &source);
+                status = status.max(EX_DATAERR);
+                continue;
+            }
+        };
+
+        if check {
+            if formatted != source {
+                eprintln!("rlox: `{file}` is not formatted");
+                status = status.max(EX_UNFORMATTED);
+            }
+        } else if path == "-" {
+            print!("{formatted}");
+        } else if formatted != source {
+            if let Err(e) = fs::write(path, formatted) {
+                eprintln!("rlox: cannot write `{path}`: {e}");
+                status = status.max(EX_IOERR);
+            }
+        }
+    }
+    ExitCode::from(status)
+}
+
+fn run_source(options: &Options, file: &str, source: String, args: Vec<String>) -> ExitCode {
+    let mut lox = options.lox(args);
+
+    match lox.eval_file(file, &source) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(errs) => {
+            if let Some(code) = exit_status(&errs) {
+                return code;
+            }
+            options.report(&errs, file, &source);
+            if errs.iter().any(Error::is_runtime) {
+                ExitCode::from(EX_SOFTWARE)
+            } else {
+                ExitCode::from(EX_DATAERR)
+            }
+        }
+    }
+}
+
+fn run_repl(options: &Options) -> ExitCode {
+    println!("Welcome to Lox interpreter!");
+    println!("Lox REPL (enter `exit` to quit)");
+
+    let mut lox = options.lox(Vec::default());
+
+    // Each input is named separately, so that an error raised while running
+    // code from an earlier input does not quote the wrong line.
+    for n in 1.. {
+        let Ok(input) = Input::<String>::with_theme(&ColorfulTheme::default())
+            .with_prompt("> ")
+            .interact_text()
+        else {
+            break;
+        };
+
+        let file = format!("<repl:{n}>");
+        match input.trim().to_lowercase().as_str() {
+            "exit" => break,
+            _ => match lox.eval_file(&file, &input) {
+                Ok(output) => println!("{output}"),
+                Err(errs) => match exit_status(&errs) {
+                    Some(code) => return code,
+                    None => options.report(&errs, &file, &input),
+                },
+            },
+        };
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// The status requested by a script that called `exit`, if it did.
+fn exit_status(errs: &[Error]) -> Option<ExitCode> {
+    errs.iter().find_map(|e| match e {
+        Error::Exit(code) => Some(ExitCode::from(*code)),
+        _ => None,
+    })
+}
@@ -0,0 +1,126 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::rc::Rc;
+
+use super::{
+    environment::Environment,
+    error::Error,
+    expression::{Expr, Function},
+    values::LoxValue,
+    Completion, LoxInterpreter,
+};
+
+/// The number of arguments a callable accepts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arity {
+    Fixed(usize),
+    Variadic,
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match self {
+            Self::Fixed(arity) => *arity == count,
+            Self::Variadic => true,
+        }
+    }
+}
+
+impl Display for Arity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fixed(arity) => write!(f, "{arity}"),
+            Self::Variadic => write!(f, "any number of"),
+        }
+    }
+}
+
+/// A value that can be invoked with `callee(args)`.
+pub(crate) trait Callable {
+    fn arity(&self) -> Arity;
+
+    /// What backtraces call a frame running the callable.
+    fn frame_name(&self) -> String;
+
+    /// Invokes the callable from the call expression `call` with arguments
+    /// whose count has already been checked against [`Callable::arity`].
+    fn call(&self, interpreter: &mut LoxInterpreter, call: &Expr, args: Vec<LoxValue>) -> Result<LoxValue, Error>;
+}
+
+/// A function declared in Lox, along with the scope it was declared in.
+pub struct LoxFunction {
+    declaration: Rc<Function>,
+    closure: Environment,
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(declaration: Rc<Function>, closure: Environment) -> Self {
+        Self {
+            declaration,
+            closure,
+            is_initializer: false,
+        }
+    }
+
+    /// Creates a method of a class. An `init` method is the class's
+    /// initializer, which always returns the instance it initialized.
+    pub fn method(declaration: Rc<Function>, closure: Environment) -> Self {
+        let is_initializer = matches!(&declaration.name, Some(name) if name.lexeme == "init");
+        Self {
+            declaration,
+            closure,
+            is_initializer,
+        }
+    }
+
+    /// Binds the method to `instance`, which its body refers to as `this`.
+    pub fn bind(&self, instance: LoxValue) -> Self {
+        let closure = Environment::enclose(&self.closure);
+        closure.define("this".to_string(), instance);
+        Self {
+            declaration: self.declaration.clone(),
+            closure,
+            is_initializer: self.is_initializer,
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.declaration.name.as_ref().map(|name| name.lexeme.as_str())
+    }
+}
+
+impl Callable for LoxFunction {
+    fn arity(&self) -> Arity {
+        Arity::Fixed(self.declaration.params.len())
+    }
+
+    fn frame_name(&self) -> String {
+        self.name().unwrap_or("<lambda>").to_string()
+    }
+
+    fn call(&self, interpreter: &mut LoxInterpreter, _call: &Expr, args: Vec<LoxValue>) -> Result<LoxValue, Error> {
+        let environment = Environment::enclose(&self.closure);
+        for (param, arg) in self.declaration.params.iter().zip(args) {
+            environment.define(param.lexeme.clone(), arg);
+        }
+
+        let completion = interpreter.execute_block(&self.declaration.body, environment)?;
+        if self.is_initializer {
+            return Ok(self.closure.get_at(0, "this").unwrap_or(LoxValue::Nil));
+        }
+        match completion {
+            Completion::Return(value) => Ok(value),
+            _ => Ok(LoxValue::Nil),
+        }
+    }
+}
+
+// The closure can refer back to the function itself, so it is left out.
+impl Debug for LoxFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoxFunction")
+            .field("name", &self.name())
+            .field("arity", &self.arity())
+            .finish()
+    }
+}
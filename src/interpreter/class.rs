@@ -0,0 +1,111 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::rc::Rc;
+
+use super::{
+    callable::{Arity, Callable, LoxFunction},
+    error::Error,
+    expression::Expr,
+    values::LoxValue,
+    LoxInterpreter,
+};
+
+pub struct LoxClass {
+    pub name: String,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<String, Rc<LoxFunction>>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: String,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<String, Rc<LoxFunction>>,
+    ) -> Self {
+        Self {
+            name,
+            superclass,
+            methods,
+        }
+    }
+
+    /// Looks a method up on this class, then on each superclass in turn.
+    pub fn find_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        match self.methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => self.superclass.as_ref()?.find_method(name),
+        }
+    }
+}
+
+// Calling a class instantiates it, so the instance needs the `Rc` to point
+// back at its class.
+impl Callable for Rc<LoxClass> {
+    fn arity(&self) -> Arity {
+        self.find_method("init")
+            .map_or(Arity::Fixed(0), |init| init.arity())
+    }
+
+    fn frame_name(&self) -> String {
+        format!("{}.init", self.name)
+    }
+
+    fn call(&self, interpreter: &mut LoxInterpreter, call: &Expr, args: Vec<LoxValue>) -> Result<LoxValue, Error> {
+        let instance = LoxValue::Instance(Rc::new(RefCell::new(LoxInstance::new(self.clone()))));
+        if let Some(init) = self.find_method("init") {
+            init.bind(instance.clone()).call(interpreter, call, args)?;
+        }
+        Ok(instance)
+    }
+}
+
+impl Debug for LoxClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoxClass")
+            .field("name", &self.name)
+            .field("superclass", &self.superclass)
+            .field("methods", &self.methods.keys())
+            .finish()
+    }
+}
+
+pub struct LoxInstance {
+    pub class: Rc<LoxClass>,
+    fields: HashMap<String, LoxValue>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        Self {
+            class,
+            fields: HashMap::default(),
+        }
+    }
+
+    /// Looks up a field of `instance`, falling back to a method of its class
+    /// bound to `instance`.
+    pub fn get(instance: &Rc<RefCell<Self>>, name: &str) -> Option<LoxValue> {
+        if let Some(value) = instance.borrow().fields.get(name) {
+            return Some(value.clone());
+        }
+
+        let method = instance.borrow().class.find_method(name)?;
+        let bound = method.bind(LoxValue::Instance(instance.clone()));
+        Some(LoxValue::Function(Rc::new(bound)))
+    }
+
+    pub fn set(&mut self, name: String, value: LoxValue) {
+        self.fields.insert(name, value);
+    }
+}
+
+// Fields can refer back to the instance itself, so only their names are shown.
+impl Debug for LoxInstance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoxInstance")
+            .field("class", &self.class.name)
+            .field("fields", &self.fields.keys())
+            .finish()
+    }
+}
@@ -0,0 +1,383 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use super::{error::Frame, span::Span};
+
+/// Labels spanning more lines than this only have their first and last
+/// lines quoted.
+const MAX_LABEL_LINES: usize = 4;
+
+/// How many columns a tab is expanded to when quoting source.
+const TAB_WIDTH: usize = 4;
+
+/// The most frames of a backtrace shown at each of its ends.
+const MAX_BACKTRACE_FRAMES: usize = 10;
+
+/// A span of source pointed at by a [`Diagnostic`], with a message about it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    /// Whether the label marks what the diagnostic is about, rather than
+    /// something giving context.
+    pub primary: bool,
+}
+
+/// An error described for reading alongside the source it was found in.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Diagnostic {
+    /// The stable code of the kind of error, as given by [`Error::code`].
+    ///
+    /// [`Error::code`]: crate::Error::code
+    pub code: Option<&'static str>,
+    pub message: String,
+    /// The primary label, if any, followed by the secondary ones.
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+    /// What was running when a runtime error was raised, innermost first.
+    pub backtrace: Vec<Frame>,
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Adds the primary label.
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.insert(
+            0,
+            Label {
+                span,
+                message: message.into(),
+                primary: true,
+            },
+        );
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: false,
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn with_backtrace(mut self, backtrace: Vec<Frame>) -> Self {
+        self.backtrace = backtrace;
+        self
+    }
+
+    /// The span the diagnostic is about.
+    pub fn span(&self) -> Option<&Span> {
+        self.labels.iter().find(|l| l.primary).map(|l| &l.span)
+    }
+
+    /// Renders the diagnostic, quoting the lines of `source`, read from
+    /// `file`, that its labels point at, and coloring it with ANSI escape
+    /// codes when `color` is set.
+    ///
+    /// ```
+    /// # use rlox::Lox;
+    /// let source = "print true + 1;";
+    /// let errs = Lox::new().eval_file("sum.lox", source).unwrap_err();
+    /// let rendered = errs[0].diagnostic().render("sum.lox", source, false);
+    /// assert_eq!(
+    ///     rendered,
+    ///     "\
+    /// error[E0010]: Incompatible types for `+`: bool and int
+    ///  --> sum.lox:1:12
+    ///   |
+    /// 1 | print true + 1;
+    ///   |            ^ operator cannot combine bool and int
+    ///   |       ---- left operand is bool
+    ///   |              - right operand is int
+    /// "
+    /// );
+    /// ```
+    pub fn render(&self, file: &str, source: &str, color: bool) -> String {
+        let style = Style(color);
+        let lines = Lines::new(source);
+
+        // Labels pointing into other sources, or past the end of this one,
+        // are left out of the quoted snippet.
+        let quoted = self
+            .labels
+            .iter()
+            .filter(|l| &*l.span.file == file && lines.contains(&l.span))
+            .collect::<Vec<&Label>>();
+
+        // The underlines on each quoted line, with the label's message on
+        // the last line it spans.
+        let mut marks: BTreeMap<usize, Vec<(usize, usize, &Label, bool)>> = BTreeMap::new();
+        for label in quoted.iter() {
+            let first = lines.index(label.span.start);
+            let last = lines.index(label.span.end.max(label.span.start + 1) - 1).max(first);
+            for line in first..=last {
+                if last - first >= MAX_LABEL_LINES && line != first && line != last {
+                    continue;
+                }
+                let (start, end) = lines.columns(line, &label.span);
+                marks.entry(line).or_default().push((start, end, label, line == last));
+            }
+        }
+
+        let width = marks.keys().last().map_or(1, |&line| (line + 1).to_string().len());
+        let pad = " ".repeat(width);
+        let gutter = style.paint(Style::GUTTER, "|");
+
+        let severity = match self.code {
+            Some(code) => format!("error[{code}]"),
+            None => "error".to_string(),
+        };
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{}{}",
+            style.paint(Style::ERROR, &severity),
+            style.paint(Style::BOLD, &format!(": {}", self.message)),
+        );
+        if let Some(span) = self.span() {
+            let _ = writeln!(
+                out,
+                "{pad}{} {}:{}:{}",
+                style.paint(Style::GUTTER, "-->"),
+                span.file,
+                span.line,
+                span.column
+            );
+        }
+        if !marks.is_empty() {
+            let _ = writeln!(out, "{pad} {gutter}");
+        }
+        let mut previous = None;
+        for (line, line_marks) in marks.iter_mut() {
+            if previous.is_some_and(|previous| line - previous > 1) {
+                let _ = writeln!(out, "{}", style.paint(Style::GUTTER, "..."));
+            }
+            previous = Some(*line);
+            let number = style.paint(Style::GUTTER, &format!("{:>width$}", line + 1));
+            let _ = writeln!(out, "{number} {gutter} {}", lines.text(*line));
+
+            line_marks.sort_by_key(|(start, _, label, _)| (!label.primary, *start));
+            for (start, end, label, last) in line_marks.iter() {
+                let (code, mark) = match label.primary {
+                    true => (Style::ERROR, "^"),
+                    false => (Style::SECONDARY, "-"),
+                };
+                let mut underline = mark.repeat((end - start).max(1));
+                if *last && !label.message.is_empty() {
+                    underline = format!("{underline} {}", label.message);
+                }
+                let _ = writeln!(
+                    out,
+                    "{pad} {gutter} {}{}",
+                    " ".repeat(*start),
+                    style.paint(code, &underline)
+                );
+            }
+        }
+        for note in self.notes.iter() {
+            let _ = writeln!(out, "{pad} = {}: {note}", style.paint(Style::BOLD, "note"));
+        }
+        if let Some(help) = &self.help {
+            let _ = writeln!(out, "{pad} = {}: {help}", style.paint(Style::BOLD, "help"));
+        }
+        // A backtrace of the script alone says nothing the location has not.
+        if self.backtrace.len() > 1 {
+            let _ = writeln!(out, "{pad} = {}:", style.paint(Style::BOLD, "backtrace"));
+            // Deep recursion leaves the same few frames repeated at length.
+            let skipped = self.backtrace.len().saturating_sub(2 * MAX_BACKTRACE_FRAMES);
+            for (i, frame) in self.backtrace.iter().enumerate() {
+                if i == MAX_BACKTRACE_FRAMES && skipped > 0 {
+                    let _ = writeln!(out, "{pad}     ... {skipped} frames omitted");
+                }
+                if i < MAX_BACKTRACE_FRAMES || i >= MAX_BACKTRACE_FRAMES + skipped {
+                    let _ = writeln!(out, "{pad}     {i}: {frame}");
+                }
+            }
+        }
+        out
+    }
+
+    /// Renders the diagnostic as a single line of JSON, for tools to read.
+    ///
+    /// ```
+    /// # use rlox::Lox;
+    /// let errs = Lox::new().eval_file("greet.lox", "print greeting;").unwrap_err();
+    /// assert_eq!(
+    ///     errs[0].diagnostic().to_json(),
+    ///     concat!(
+    ///         r#"{"code":"E0013","severity":"error","message":"Undefined variable greeting","#,
+    ///         r#""file":"greet.lox","span":{"file":"greet.lox","start":6,"end":14,"#,
+    ///         r#""line":1,"column":7,"end_line":1,"end_column":15},"#,
+    ///         r#""labels":[{"primary":true,"message":"not defined","span":{"file":"greet.lox","start":6,"end":14,"#,
+    ///         r#""line":1,"column":7,"end_line":1,"end_column":15}}],"notes":[],"help":null,"#,
+    ///         r#""backtrace":[{"name":"<script>","span":{"file":"greet.lox","start":6,"end":14,"#,
+    ///         r#""line":1,"column":7,"end_line":1,"end_column":15}}]}"#,
+    ///     )
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        let span = |span: &Span| {
+            format!(
+                r#"{{"file":{},"start":{},"end":{},"line":{},"column":{},"end_line":{},"end_column":{}}}"#,
+                json_string(&span.file),
+                span.start,
+                span.end,
+                span.line,
+                span.column,
+                span.end_line,
+                span.end_column,
+            )
+        };
+        let labels = self
+            .labels
+            .iter()
+            .map(|l| {
+                format!(
+                    r#"{{"primary":{},"message":{},"span":{}}}"#,
+                    l.primary,
+                    json_string(&l.message),
+                    span(&l.span)
+                )
+            })
+            .collect::<Vec<String>>();
+        let notes = self.notes.iter().map(|n| json_string(n)).collect::<Vec<String>>();
+        let backtrace = self
+            .backtrace
+            .iter()
+            .map(|f| format!(r#"{{"name":{},"span":{}}}"#, json_string(&f.name), span(&f.span)))
+            .collect::<Vec<String>>();
+        format!(
+            r#"{{"code":{},"severity":"error","message":{},"file":{},"span":{},"labels":[{}],"notes":[{}],"help":{},"backtrace":[{}]}}"#,
+            self.code.map_or("null".to_string(), json_string),
+            json_string(&self.message),
+            self.span().map_or("null".to_string(), |s| json_string(&s.file)),
+            self.span().map_or("null".to_string(), span),
+            labels.join(","),
+            notes.join(","),
+            self.help.as_deref().map_or("null".to_string(), json_string),
+            backtrace.join(","),
+        )
+    }
+}
+
+/// Quotes `s` as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Whether rendered output is colored.
+#[derive(Clone, Copy)]
+struct Style(bool);
+
+impl Style {
+    const BOLD: &'static str = "1";
+    const ERROR: &'static str = "1;31";
+    const SECONDARY: &'static str = "1;34";
+    const GUTTER: &'static str = "1;34";
+
+    fn paint(self, code: &str, text: &str) -> String {
+        match self.0 {
+            true => format!("\x1b[{code}m{text}\x1b[0m"),
+            false => text.to_string(),
+        }
+    }
+}
+
+/// The lines of a source, found by the byte offsets they start at.
+struct Lines<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> Lines<'a> {
+    fn new(source: &'a str) -> Self {
+        let breaks = source.match_indices('\n').map(|(i, _)| i + 1);
+        Self {
+            source,
+            starts: std::iter::once(0).chain(breaks).collect(),
+        }
+    }
+
+    fn contains(&self, span: &Span) -> bool {
+        span.start <= span.end
+            && span.end <= self.source.len()
+            && self.source.is_char_boundary(span.start)
+            && self.source.is_char_boundary(span.end)
+    }
+
+    /// The index of the line holding the byte at `offset`.
+    fn index(&self, offset: usize) -> usize {
+        self.starts.partition_point(|&start| start <= offset) - 1
+    }
+
+    /// The byte range of line `index`, without its line break.
+    fn range(&self, index: usize) -> (usize, usize) {
+        let start = self.starts[index];
+        let end = match self.starts.get(index + 1) {
+            Some(next) => next - 1,
+            None => self.source.len(),
+        };
+        let end = match self.source[start..end].ends_with('\r') {
+            true => end - 1,
+            false => end,
+        };
+        (start, end)
+    }
+
+    /// Line `index` as it is quoted, with tabs expanded.
+    fn text(&self, index: usize) -> String {
+        let (start, end) = self.range(index);
+        self.source[start..end].replace('\t', &" ".repeat(TAB_WIDTH))
+    }
+
+    /// The columns of line `index`, as quoted, covered by `span`.
+    fn columns(&self, index: usize, span: &Span) -> (usize, usize) {
+        let (start, end) = self.range(index);
+        let width = |to: usize| {
+            self.source[start..to.clamp(start, end)]
+                .chars()
+                .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+                .sum::<usize>()
+        };
+        (width(span.start), width(span.end))
+    }
+}
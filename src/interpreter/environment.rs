@@ -1,74 +1,88 @@
-use std::{collections::BTreeMap, rc::Rc, sync::RwLock};
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
 
 use super::values::LoxValue;
 
 #[derive(Default)]
-pub struct Environment {
-    inner: Rc<BTreeMap<String, LoxValue>>,
-    enclosing: Option<Rc<Environment>>,
-	enclosed: Option<Rc<RwLock<>
+struct Scope {
+    values: BTreeMap<String, LoxValue>,
+    enclosing: Option<Environment>,
 }
 
-impl Clone for Environment {
-    fn clone(&self) -> Self {
-        Self {
-            inner: Rc::clone(&self.inner),
-            enclosing: self.enclosing.clone(),
-        }
-    }
+/// A handle to one scope of the scope chain.
+///
+/// Clones share the underlying scope, so anything that captures an
+/// `Environment` observes later definitions and assignments made through
+/// any other handle to it.
+#[derive(Clone, Default)]
+pub struct Environment {
+    scope: Rc<RefCell<Scope>>,
 }
 
 impl Environment {
-    pub fn enclose(env: Rc<Environment>) -> Self {
+    /// Creates an empty scope nested inside `enclosing`.
+    pub fn enclose(enclosing: &Environment) -> Self {
         Self {
-            inner: Rc::default(),
-            enclosing: Some(env),
+            scope: Rc::new(RefCell::new(Scope {
+                values: BTreeMap::default(),
+                enclosing: Some(enclosing.clone()),
+            })),
         }
     }
 
-    pub fn drop_enclusure(self) -> Self {
-        *Rc::as_ref(&self.enclosing.unwrap())
+    /// Binds `name` in this scope, shadowing any binding in enclosing scopes.
+    pub fn define(&self, name: String, value: LoxValue) {
+        self.scope.borrow_mut().values.insert(name, value);
     }
 
-    pub fn define(&mut self, name: String, value: LoxValue) {
-        let env = Rc::get_mut(&mut self.inner).unwrap();
-        env.insert(name, value);
+    /// Looks `name` up, starting at this scope and walking outwards.
+    pub fn get(&self, name: &str) -> Option<LoxValue> {
+        let scope = self.scope.borrow();
+        match scope.values.get(name) {
+            Some(value) => Some(value.clone()),
+            None => scope.enclosing.as_ref()?.get(name),
+        }
     }
 
-    pub fn get(&self, name: &String) -> Option<&LoxValue> {
-        let env = Rc::as_ref(&self.inner);
-        if let Some(val) = env.get(name) {
-            return Some(val);
-        }
+    /// Looks `name` up in the scope `depth` scopes out from this one, without
+    /// walking any further.
+    pub fn get_at(&self, depth: usize, name: &str) -> Option<LoxValue> {
+        self.ancestor(depth)?.scope.borrow().values.get(name).cloned()
+    }
 
-        match &self.enclosing {
-            None => None,
-            Some(env) => {
-                let env = Rc::as_ref(env);
+    /// Assigns to `name` in the scope `depth` scopes out from this one,
+    /// returning the value it replaced, or `None` if it is not bound there.
+    pub fn update_at(&self, depth: usize, name: &str, value: LoxValue) -> Option<LoxValue> {
+        let ancestor = self.ancestor(depth)?;
+        let mut scope = ancestor.scope.borrow_mut();
+        scope.values.get_mut(name).map(|slot| std::mem::replace(slot, value))
+    }
 
-                match env.get(name) {
-                    None => self.inner.get(name),
-                    val => val,
-                }
-            }
+    /// Every name bound in this scope or an enclosing one, innermost first.
+    pub fn names(&self) -> Vec<String> {
+        let scope = self.scope.borrow();
+        let mut names = scope.values.keys().cloned().collect::<Vec<String>>();
+        if let Some(enclosing) = &scope.enclosing {
+            names.extend(enclosing.names());
         }
+        names
     }
 
-    pub fn update(&mut self, name: String, value: LoxValue) -> Option<LoxValue> {
-        match &mut self.enclosing {
-            None => self.update_inner(name, value),
-            Some(env) => {
-                let env = Rc::get_mut(env).unwrap();
-                env.update(name, value)
-            }
+    fn ancestor(&self, depth: usize) -> Option<Environment> {
+        let mut environment = self.clone();
+        for _ in 0..depth {
+            let enclosing = environment.scope.borrow().enclosing.clone()?;
+            environment = enclosing;
         }
+        Some(environment)
     }
 
-    fn update_inner(&mut self, name: String, value: LoxValue) -> Option<LoxValue> {
-        if let None = self.inner.get(&name) {
-            return None;
+    /// Assigns to the nearest binding of `name`, returning the value it
+    /// replaced, or `None` if `name` is not bound in any scope.
+    pub fn update(&self, name: &str, value: LoxValue) -> Option<LoxValue> {
+        let mut scope = self.scope.borrow_mut();
+        match scope.values.get_mut(name) {
+            Some(slot) => Some(std::mem::replace(slot, value)),
+            None => scope.enclosing.as_ref()?.update(name, value),
         }
-        let env = Rc::get_mut(&mut self.inner).unwrap();
-        env.insert(name, value)
     }
 }
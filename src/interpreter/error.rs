@@ -1,65 +1,142 @@
 use std::fmt::{Display, Formatter, Result};
 
-use super::{expression::*, token::Token, values::LoxType};
+use super::{diagnostic::Diagnostic, expression::*, span::Span, values::LoxType};
 
 #[derive(Debug, Clone)]
 pub enum Error {
-	UnexpectedToken(i32, String),
-	UnterminatedString(i32),
-	UnparsableNumber(i32, String),
-	WrongTokenType(i32, String, String),
-	InvalidAssignmentTarget(i32, String),
-	UnexpectedEof(i32),
-	WrongType(i32, Expr, LoxType, LoxType),
-	IncompatibleTypes(i32, Expr, LoxType, LoxType),
-	InoperableTypes(Token, Expr, Vec<LoxType>, LoxType, LoxType),
-	RuntimeError(i32, Expr, String),
-	UndefinedVariable(i32, String), // Eof,
+	UnexpectedToken(Span, String),
+	UnterminatedString(Span),
+	UnterminatedComment(Span),
+	InvalidEscape(Span, String),
+	UnparsableNumber(Span, String),
+	WrongTokenType(Span, String, String),
+	InvalidAssignmentTarget(Span, String),
+	UnexpectedEof(Span),
+	WrongType(Span, Expr, LoxType, Vec<LoxType>),
+	IncompatibleTypes(Span, Expr, LoxType, LoxType),
+	InoperableTypes(Span, Expr, Vec<LoxType>, LoxType, LoxType),
+	RuntimeError(Span, Expr, String),
+	/// An undefined variable, with the defined name or keyword it is
+	/// probably a misspelling of.
+	UndefinedVariable(Span, String, Option<String>),
+	OutsideLoop(Span, String),
+	ReadInOwnInitializer(Span, String),
+	AlreadyDeclared(Span, String),
+	ReturnOutsideFunction(Span),
+	ReturnValueFromInitializer(Span),
+	ThisOutsideClass(Span),
+	SuperOutsideSubclass(Span),
+	InheritsFromSelf(Span, String),
+	Exit(u8), // Eof,
+	/// Source the formatter could not lay out without changing how it
+	/// parses.
+	Unformattable(Span),
+	/// A runtime error, along with what was running when it was raised.
+	WithBacktrace(Box<Error>, Vec<Frame>),
+}
+
+/// A construct that was running when a runtime error was raised.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+	/// `<script>` or `<block>`, or the name of a function being called.
+	pub name: String,
+	/// Where in the construct evaluation had got to: the error itself in the
+	/// innermost frame, and the construct entered next in the others.
+	pub span: Span,
+}
+
+impl Display for Frame {
+	fn fmt(&self, fmt: &mut Formatter<'_>) -> Result {
+		let Frame { name, span } = self;
+		write!(fmt, "{name} at {}:{}:{}", span.file, span.line, span.column)
+	}
 }
 
 impl Display for Error {
 	fn fmt(&self, fmt: &mut Formatter<'_>) -> Result {
 		let mut ast_printer = AstPrinter;
 		match self {
-			Self::UnexpectedToken(line, token) => {
-				write!(fmt, "[line {line}] Error: Unexpected token {token}.")
+			Self::UnexpectedToken(span, token) => {
+				write!(fmt, "[{span}] Error: Unexpected token {token}.")
+			}
+			Self::UnterminatedString(span) => {
+				write!(fmt, "[{span}] Error: Unterminated string literal.")
 			}
-			Self::UnterminatedString(line) => {
-				write!(fmt, "[line {line}] Error: Unterminated string literal.")
+			Self::UnterminatedComment(span) => {
+				write!(fmt, "[{span}] Error: Unterminated block comment.")
 			}
-			Self::UnparsableNumber(line, message) => write!(fmt, "[line {line}] Error: {message}."),
-			Self::WrongTokenType(line, actual, expected) => write!(
+			Self::InvalidEscape(span, escape) => {
+				write!(fmt, "[{span}] Error: Invalid escape sequence {escape}.")
+			}
+			Self::UnparsableNumber(span, message) => write!(fmt, "[{span}] Error: {message}."),
+			Self::WrongTokenType(span, actual, expected) => write!(
 				fmt,
-				"[line {line}] Error: Expected token type {expected}, found {actual}"
+				"[{span}] Error: Expected token type {expected}, found {actual}"
 			),
-			Self::InvalidAssignmentTarget(line, target) => write!(fmt, "[line {line}] Error: Invalid Assignment Target: {target}."),
-			Self::WrongType(line, expr, actual, expected) => write!(
+			Self::InvalidAssignmentTarget(span, target) => write!(fmt, "[{span}] Error: Invalid Assignment Target: {target}."),
+			Self::WrongType(span, expr, actual, expected) => write!(
 				fmt,
-				"[line {line}] Error in expression: {:}:\nExpected type {expected}, found {actual}",
+				"[{span}] Error in expression: {:}:\nExpected type {}, found {actual}",
 				expr.accept(&mut ast_printer).unwrap(),
+				alternatives(expected),
 			),
-			Self::IncompatibleTypes(line, expr, left, right) => write!(
+			Self::IncompatibleTypes(span, expr, left, right) => write!(
 				fmt,
-				"[line {line}] Incompatible types in expression: {:}: {left}, {right}",
+				"[{span}] Incompatible types in expression: {:}: {left}, {right}",
 				expr.accept(&mut ast_printer).unwrap(),
 			),
-			Self::InoperableTypes(op, expr, supported_types, left, right) => write!(
+			Self::InoperableTypes(span, expr, supported_types, left, right) => write!(
 				fmt,
-				"[line {:}] Operator cannot be used on types in expression: {:}: {left}, {right}. Supported types: {:}",
-				op.line,
+				"[{span}] Operator cannot be used on types in expression: {:}: {left}, {right}. Supported types: {:}",
 				expr.accept(&mut ast_printer).unwrap(),
 				supported_types.iter().map(ToOwned::to_owned).map(String::from).collect::<Vec<_>>().join(", "),
 			),
-			Self::RuntimeError(line, expr, err) => write!(
+			Self::RuntimeError(span, expr, err) => write!(
 				fmt,
-				"[line {line}] Error in expression: {:}:\n{err}",
+				"[{span}] Error in expression: {:}:\n{err}",
 				expr.accept(&mut ast_printer).unwrap(),
 			),
-			Self::UndefinedVariable(line, var) => write!(
+			Self::UndefinedVariable(span, var, _) => write!(
+				fmt,
+				"[{span}] Undefined variable: {var}",
+			),
+			Self::OutsideLoop(span, keyword) => {
+				write!(fmt, "[{span}] Error: '{keyword}' outside of a loop.")
+			}
+			Self::ReadInOwnInitializer(span, var) => write!(
+				fmt,
+				"[{span}] Error: Cannot read local variable {var} in its own initializer."
+			),
+			Self::AlreadyDeclared(span, var) => write!(
+				fmt,
+				"[{span}] Error: Variable {var} is already declared in this scope."
+			),
+			Self::ReturnOutsideFunction(span) => {
+				write!(fmt, "[{span}] Error: Cannot return from top-level code.")
+			}
+			Self::ReturnValueFromInitializer(span) => {
+				write!(fmt, "[{span}] Error: Cannot return a value from an initializer.")
+			}
+			Self::ThisOutsideClass(span) => {
+				write!(fmt, "[{span}] Error: Cannot use 'this' outside of a class.")
+			}
+			Self::SuperOutsideSubclass(span) => write!(
 				fmt,
-				"[line {line}] Undefined variable: {var}",
+				"[{span}] Error: Cannot use 'super' outside of a class with a superclass."
 			),
-			Self::UnexpectedEof(line) => write!(fmt, "[line {line}] Error: Unexpected EOF."),
+			Self::InheritsFromSelf(span, class) => {
+				write!(fmt, "[{span}] Error: Class {class} cannot inherit from itself.")
+			}
+			Self::Exit(code) => write!(fmt, "Exited with status {code}."),
+			Self::Unformattable(span) => write!(fmt, "[{span}] Error: Formatting would change the program."),
+			Self::WithBacktrace(error, backtrace) => {
+				write!(fmt, "{error}")?;
+				for frame in backtrace.iter() {
+					write!(fmt, "\n    in {frame}")?;
+				}
+				Ok(())
+			}
+			Self::UnexpectedEof(span) => write!(fmt, "[{span}] Error: Unexpected EOF."),
 			// Self::Eof => write!(fmt, "Error: EOF"),
 		}
 	}
@@ -71,4 +148,251 @@ impl Error {
 	pub fn to_result<V>(self) -> std::result::Result<V, Self> {
 		Err(self)
 	}
+
+	/// Where in the source the error was raised, if anywhere.
+	pub fn span(&self) -> Option<&Span> {
+		match self {
+			Self::UnexpectedToken(span, ..)
+			| Self::UnterminatedString(span)
+			| Self::UnterminatedComment(span)
+			| Self::InvalidEscape(span, ..)
+			| Self::UnparsableNumber(span, ..)
+			| Self::WrongTokenType(span, ..)
+			| Self::InvalidAssignmentTarget(span, ..)
+			| Self::UnexpectedEof(span)
+			| Self::WrongType(span, ..)
+			| Self::IncompatibleTypes(span, ..)
+			| Self::InoperableTypes(span, ..)
+			| Self::RuntimeError(span, ..)
+			| Self::UndefinedVariable(span, ..)
+			| Self::OutsideLoop(span, ..)
+			| Self::ReadInOwnInitializer(span, ..)
+			| Self::AlreadyDeclared(span, ..)
+			| Self::ReturnOutsideFunction(span)
+			| Self::ReturnValueFromInitializer(span)
+			| Self::ThisOutsideClass(span)
+			| Self::SuperOutsideSubclass(span)
+			| Self::InheritsFromSelf(span, ..)
+			| Self::Unformattable(span) => Some(span),
+			Self::Exit(_) => None,
+			Self::WithBacktrace(error, _) => error.span(),
+		}
+	}
+
+	/// Describes the error for rendering alongside its source.
+	pub fn diagnostic(&self) -> Diagnostic {
+		// The start of a span running to the end of the source, such as an
+		// unterminated string's.
+		let opening = |span: &Span| Span {
+			end: span.start + 1,
+			end_line: span.line,
+			end_column: span.column + 1,
+			..span.clone()
+		};
+		let types = |types: &[LoxType]| types.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(", ");
+		let diagnostic = match self {
+			Self::UnexpectedToken(span, token) => {
+				Diagnostic::new(format!("Unexpected token {token}")).with_label(span.clone(), "")
+			}
+			Self::UnterminatedString(span) => Diagnostic::new("Unterminated string literal")
+				.with_label(opening(span), "string starts here")
+				.with_help("close the string with the quotes it was opened with"),
+			Self::UnterminatedComment(span) => Diagnostic::new("Unterminated block comment")
+				.with_label(opening(span), "comment starts here")
+				.with_note("block comments nest, so each `/*` needs its own `*/`"),
+			Self::InvalidEscape(span, escape) => Diagnostic::new(format!("Invalid escape sequence {escape}"))
+				.with_label(span.clone(), "invalid escape")
+				.with_help(r#"the escapes are \n, \t, \r, \0, \\, \", \', \$ and \u{...}; raw strings such as r"\d" have none"#),
+			Self::UnparsableNumber(span, message) => Diagnostic::new(message).with_label(span.clone(), ""),
+			Self::WrongTokenType(span, actual, expected) => {
+				Diagnostic::new(format!("Expected {expected}, found {actual}"))
+					.with_label(span.clone(), format!("expected {expected}"))
+			}
+			Self::InvalidAssignmentTarget(span, target) => {
+				Diagnostic::new(format!("Invalid assignment target {target}"))
+					.with_label(span.clone(), "cannot assign to the expression before this")
+					.with_note("only variables, properties and list or map items can be assigned to")
+			}
+			Self::UnexpectedEof(span) => {
+				Diagnostic::new("Unexpected end of input").with_label(span.clone(), "expected more input")
+			}
+			Self::WrongType(span, expr, actual, expected) => {
+				let expected = alternatives(expected);
+				let diagnostic = Diagnostic::new(format!("Expected type {expected}, found {actual}"))
+					.with_label(span.clone(), format!("expected {expected}"));
+				match expr.span() {
+					operand if operand != *span => diagnostic.with_secondary(operand, format!("found {actual}")),
+					_ => diagnostic,
+				}
+			}
+			Self::IncompatibleTypes(span, expr, left, right) => {
+				let diagnostic = Diagnostic::new(match expr {
+					Expr::Binary(_, op, _) => format!("Incompatible types for `{}`: {left} and {right}", op.lexeme),
+					_ => format!("Incompatible types: {left} and {right}"),
+				})
+				.with_label(span.clone(), format!("operator cannot combine {left} and {right}"));
+				operand_labels(diagnostic, expr, left, right)
+			}
+			Self::InoperableTypes(span, expr, supported_types, left, right) => {
+				let diagnostic = Diagnostic::new(match expr {
+					Expr::Binary(_, op, _) => format!("Operator `{}` cannot be used on {left} and {right}", op.lexeme),
+					_ => format!("Operator cannot be used on {left} and {right}"),
+				})
+				.with_label(span.clone(), "operator not supported for these types")
+				.with_note(format!("supported types: {}", types(supported_types)));
+				operand_labels(diagnostic, expr, left, right)
+			}
+			Self::RuntimeError(span, _, err) => Diagnostic::new(err).with_label(span.clone(), ""),
+			Self::UndefinedVariable(span, var, suggestion) => {
+				let diagnostic =
+					Diagnostic::new(format!("Undefined variable {var}")).with_label(span.clone(), "not defined");
+				match suggestion {
+					Some(suggestion) => diagnostic.with_help(format!("did you mean `{suggestion}`?")),
+					None => diagnostic,
+				}
+			}
+			Self::OutsideLoop(span, keyword) => Diagnostic::new(format!("'{keyword}' outside of a loop"))
+				.with_label(span.clone(), format!("cannot `{keyword}` outside of a loop")),
+			Self::ReadInOwnInitializer(span, var) => {
+				Diagnostic::new(format!("Cannot read local variable {var} in its own initializer"))
+					.with_label(span.clone(), "read while being declared")
+			}
+			Self::AlreadyDeclared(span, var) => {
+				Diagnostic::new(format!("Variable {var} is already declared in this scope"))
+					.with_label(span.clone(), "declared again here")
+			}
+			Self::ReturnOutsideFunction(span) => Diagnostic::new("Cannot return from top-level code")
+				.with_label(span.clone(), "`return` outside of a function"),
+			Self::ReturnValueFromInitializer(span) => Diagnostic::new("Cannot return a value from an initializer")
+				.with_label(span.clone(), "")
+				.with_note("an initializer always returns the instance it initialized"),
+			Self::ThisOutsideClass(span) => Diagnostic::new("Cannot use 'this' outside of a class")
+				.with_label(span.clone(), "not in a method"),
+			Self::SuperOutsideSubclass(span) => {
+				Diagnostic::new("Cannot use 'super' outside of a class with a superclass")
+					.with_label(span.clone(), "no superclass here")
+			}
+			Self::InheritsFromSelf(span, class) => {
+				Diagnostic::new(format!("Class {class} cannot inherit from itself"))
+					.with_label(span.clone(), "")
+			}
+			Self::Exit(code) => Diagnostic::new(format!("Exited with status {code}")),
+			Self::Unformattable(span) => Diagnostic::new("Formatting would change the program")
+				.with_label(span.clone(), "")
+				.with_note("the file has been left as it was"),
+			Self::WithBacktrace(error, backtrace) => return error.diagnostic().with_backtrace(backtrace.clone()),
+		};
+		diagnostic.with_code(self.code())
+	}
+
+	/// The stable code identifying the kind of error, for tools to match on.
+	///
+	/// | Code  | Error |
+	/// |-------|-------|
+	/// | E0001 | A character that does not start any token, or a misplaced token |
+	/// | E0002 | A string literal missing its closing quotes |
+	/// | E0003 | A block comment missing its closing `*/` |
+	/// | E0004 | An invalid escape sequence in a string literal |
+	/// | E0005 | A malformed number literal |
+	/// | E0006 | A token other than the one the grammar requires |
+	/// | E0007 | An assignment to something other than a variable, property or item |
+	/// | E0008 | Source ending in the middle of a declaration |
+	/// | E0009 | A value of the wrong type |
+	/// | E0010 | Operands whose types cannot be combined, such as in `1 < "a"` |
+	/// | E0011 | Operands of types the operator does not support |
+	/// | E0012 | Any other runtime error, such as an index out of range |
+	/// | E0013 | An undefined variable, or a missing `this` or `super` |
+	/// | E0014 | `break` or `continue` outside of a loop |
+	/// | E0015 | A local variable read in its own initializer |
+	/// | E0016 | A variable declared twice in the same scope |
+	/// | E0017 | `return` outside of a function |
+	/// | E0018 | `return` with a value in an initializer |
+	/// | E0019 | `this` outside of a class |
+	/// | E0020 | `super` outside of a class with a superclass |
+	/// | E0021 | A class inheriting from itself |
+	/// | E0022 | A script calling `exit` |
+	/// | E0023 | Source the formatter could not lay out without changing how it parses |
+	///
+	/// Codes are never reused, and new kinds of error get new codes.
+	pub fn code(&self) -> &'static str {
+		match self {
+			Self::UnexpectedToken(..) => "E0001",
+			Self::UnterminatedString(..) => "E0002",
+			Self::UnterminatedComment(..) => "E0003",
+			Self::InvalidEscape(..) => "E0004",
+			Self::UnparsableNumber(..) => "E0005",
+			Self::WrongTokenType(..) => "E0006",
+			Self::InvalidAssignmentTarget(..) => "E0007",
+			Self::UnexpectedEof(..) => "E0008",
+			Self::WrongType(..) => "E0009",
+			Self::IncompatibleTypes(..) => "E0010",
+			Self::InoperableTypes(..) => "E0011",
+			Self::RuntimeError(..) => "E0012",
+			Self::UndefinedVariable(..) => "E0013",
+			Self::OutsideLoop(..) => "E0014",
+			Self::ReadInOwnInitializer(..) => "E0015",
+			Self::AlreadyDeclared(..) => "E0016",
+			Self::ReturnOutsideFunction(..) => "E0017",
+			Self::ReturnValueFromInitializer(..) => "E0018",
+			Self::ThisOutsideClass(..) => "E0019",
+			Self::SuperOutsideSubclass(..) => "E0020",
+			Self::InheritsFromSelf(..) => "E0021",
+			Self::Exit(..) => "E0022",
+			Self::Unformattable(..) => "E0023",
+			Self::WithBacktrace(error, _) => error.code(),
+		}
+	}
+
+	/// Whether the error was raised while evaluating, as opposed to while
+	/// tokenizing or parsing.
+	pub fn is_runtime(&self) -> bool {
+		matches!(
+			self,
+			Self::WrongType(..)
+				| Self::IncompatibleTypes(..)
+				| Self::InoperableTypes(..)
+				| Self::RuntimeError(..)
+				| Self::UndefinedVariable(..)
+				| Self::WithBacktrace(..)
+		)
+	}
+
+	/// What was running when a runtime error was raised, innermost first.
+	///
+	/// ```
+	/// # use rlox::Lox;
+	/// let source = "fun half(n) {\n  return n / 0;\n}\nprint half(4);";
+	/// let errs = Lox::new().eval_file("half.lox", source).unwrap_err();
+	/// let backtrace = errs[0].backtrace().unwrap();
+	/// let frames = backtrace.iter().map(ToString::to_string).collect::<Vec<_>>();
+	/// assert_eq!(frames, ["half at half.lox:2:12", "<script> at half.lox:4:7"]);
+	/// ```
+	pub fn backtrace(&self) -> Option<&[Frame]> {
+		match self {
+			Self::WithBacktrace(_, backtrace) => Some(backtrace),
+			_ => None,
+		}
+	}
+}
+
+/// Labels the operands of the binary expression `expr` with their types.
+fn operand_labels(diagnostic: Diagnostic, expr: &Expr, left: &LoxType, right: &LoxType) -> Diagnostic {
+	match expr {
+		Expr::Binary(l, _, r) => diagnostic
+			.with_secondary(l.span(), format!("left operand is {left}"))
+			.with_secondary(r.span(), format!("right operand is {right}")),
+		_ => diagnostic,
+	}
+}
+
+/// Lists `types` as alternatives, such as `int, float or string`.
+fn alternatives(types: &[LoxType]) -> String {
+	match types {
+		[] => String::default(),
+		[only] => only.to_string(),
+		[rest @ .., last] => {
+			let rest = rest.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+			format!("{rest} or {last}")
+		}
+	}
 }
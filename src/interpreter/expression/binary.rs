@@ -1,13 +1,14 @@
 use super::{Expr, Visitor};
 use crate::interpreter::error::Error;
-use crate::interpreter::values::{LoxType, LoxValue};
+use crate::interpreter::values::{Dialect, LoxType, LoxValue};
 use crate::interpreter::{Token, TokenType};
 
 pub fn visit_binary_expression<V>(
 	expr: &Expr,
-	left: Box<Expr>,
-	op: Token,
-	right: Box<Expr>,
+	left: &Expr,
+	op: &Token,
+	right: &Expr,
+	dialect: Dialect,
 	visitor: &mut V,
 ) -> Result<LoxValue, Error>
 where
@@ -15,9 +16,21 @@ where
 {
 	let left = left.accept(visitor)?;
 	let right = right.accept(visitor)?;
+	binary_operation(expr, left, op, right, dialect)
+}
+
+/// Applies the binary operator `op` of `expr` to two values.
+pub fn binary_operation(
+	expr: &Expr,
+	left: LoxValue,
+	op: &Token,
+	right: LoxValue,
+	dialect: Dialect,
+) -> Result<LoxValue, Error> {
+	let op = op.clone();
 	match op.token_type.clone() {
 		TokenType::BangEqual | TokenType::EqualEqual => {
-			visit_equality_expression(expr, left, op, right)
+			visit_equality_expression(expr, left, op, right, dialect)
 		}
 		TokenType::Greater
 		| TokenType::GreaterEqual
@@ -25,12 +38,20 @@ where
 		| TokenType::LessEqual => {
 			visit_comparison_expression(expr, left, op, right)
 		}
-		TokenType::Star | TokenType::Slash => {
+		TokenType::Star | TokenType::Slash | TokenType::Percent => {
 			visit_factor_expression(expr, left, op, right)
 		}
 		TokenType::Minus | TokenType::Plus => {
 			visit_term_expression(expr, left, op, right)
 		}
+		TokenType::StarStar => visit_exponent_expression(expr, left, op, right),
+		TokenType::Ampersand
+		| TokenType::Pipe
+		| TokenType::Caret
+		| TokenType::LessLess
+		| TokenType::GreaterGreater => {
+			visit_bitwise_expression(expr, left, op, right)
+		}
 		_ => unreachable!("Binary operator: {:?}", op.token_type),
 	}
 }
@@ -40,13 +61,15 @@ fn visit_equality_expression(
 	left: LoxValue,
 	op: Token,
 	right: LoxValue,
+	dialect: Dialect,
 ) -> Result<LoxValue, Error> {
-	if !left.is_nil()
+	if dialect == Dialect::Strict
+		&& !left.is_nil()
 		&& !right.is_nil()
-		&& !left.is_same_type_as(&right)
+		&& !left.is_comparable_with(&right)
 	{
 		return Err(Error::IncompatibleTypes(
-			op.line,
+			op.span.clone(),
 			expr.to_owned(),
 			left.lox_type(),
 			right.lox_type(),
@@ -69,10 +92,10 @@ fn visit_comparison_expression(
 ) -> Result<LoxValue, Error> {
 	if !left.is_nil()
 		&& !right.is_nil()
-		&& !left.is_same_type_as(&right)
+		&& !left.is_comparable_with(&right)
 	{
 		return Err(Error::IncompatibleTypes(
-			op.line,
+			op.span.clone(),
 			expr.to_owned(),
 			left.lox_type(),
 			right.lox_type(),
@@ -82,10 +105,10 @@ fn visit_comparison_expression(
 		TokenType::Greater => {
 			if left.is_nil()
 				|| right.is_nil()
-				|| !left.is_same_type_as(&right)
+				|| !left.is_comparable_with(&right)
 			{
 				return Err(Error::IncompatibleTypes(
-					op.line,
+					op.span.clone(),
 					expr.to_owned(),
 					left.lox_type(),
 					right.lox_type(),
@@ -96,10 +119,10 @@ fn visit_comparison_expression(
 		TokenType::GreaterEqual => {
 			if left.is_nil()
 				|| right.is_nil()
-				|| !left.is_same_type_as(&right)
+				|| !left.is_comparable_with(&right)
 			{
 				return Err(Error::IncompatibleTypes(
-					op.line,
+					op.span.clone(),
 					expr.to_owned(),
 					left.lox_type(),
 					right.lox_type(),
@@ -110,10 +133,10 @@ fn visit_comparison_expression(
 		TokenType::Less => {
 			if left.is_nil()
 				|| right.is_nil()
-				|| !left.is_same_type_as(&right)
+				|| !left.is_comparable_with(&right)
 			{
 				return Err(Error::IncompatibleTypes(
-					op.line,
+					op.span.clone(),
 					expr.to_owned(),
 					left.lox_type(),
 					right.lox_type(),
@@ -124,10 +147,10 @@ fn visit_comparison_expression(
 		TokenType::LessEqual => {
 			if left.is_nil()
 				|| right.is_nil()
-				|| !left.is_same_type_as(&right)
+				|| !left.is_comparable_with(&right)
 			{
 				return Err(Error::IncompatibleTypes(
-					op.line,
+					op.span.clone(),
 					expr.to_owned(),
 					left.lox_type(),
 					right.lox_type(),
@@ -141,68 +164,97 @@ fn visit_comparison_expression(
 	}
 }
 
+/// Applies an arithmetic operator to two numbers.
+///
+/// Two ints give an int, failing rather than overflowing, while an int
+/// combined with a float is promoted to a float.
+fn arithmetic(
+	expr: &Expr,
+	left: &LoxValue,
+	op: &Token,
+	right: &LoxValue,
+	int_op: fn(i64, i64) -> Option<i64>,
+	float_op: fn(f64, f64) -> f64,
+) -> Result<LoxValue, Error> {
+	match (left, right) {
+		(LoxValue::Int(l), LoxValue::Int(r)) => {
+			int_op(*l, *r).map(LoxValue::Int).ok_or_else(|| {
+				Error::RuntimeError(
+					op.span.clone(),
+					expr.to_owned(),
+					"Integer overflow".to_string(),
+				)
+			})
+		}
+		(LoxValue::Int(l), LoxValue::Float(r)) => {
+			Ok(LoxValue::Float(float_op(*l as f64, *r)))
+		}
+		(LoxValue::Float(l), LoxValue::Int(r)) => {
+			Ok(LoxValue::Float(float_op(*l, *r as f64)))
+		}
+		(LoxValue::Float(l), LoxValue::Float(r)) => {
+			Ok(LoxValue::Float(float_op(*l, *r)))
+		}
+		_ if left.is_number() || right.is_number() => {
+			Err(Error::IncompatibleTypes(
+				op.span.clone(),
+				expr.to_owned(),
+				left.lox_type(),
+				right.lox_type(),
+			))
+		}
+		_ => Err(Error::InoperableTypes(
+			op.span.clone(),
+			expr.to_owned(),
+			vec![LoxType::Int, LoxType::Float],
+			left.lox_type(),
+			right.lox_type(),
+		)),
+	}
+}
+
 fn visit_factor_expression(
 	expr: &Expr,
 	left: LoxValue,
 	op: Token,
 	right: LoxValue,
 ) -> Result<LoxValue, Error> {
+	if matches!(op.token_type, TokenType::Slash | TokenType::Percent)
+		&& (right == LoxValue::Int(0) || right == LoxValue::Float(0.0))
+	{
+		return Err(Error::RuntimeError(
+			op.span.clone(),
+			expr.to_owned(),
+			"Divide by zero".to_string(),
+		));
+	}
 	match op.token_type.clone() {
-		TokenType::Star => match (&left, &right) {
-			(LoxValue::Number(l), LoxValue::Number(r)) => {
-				Ok(LoxValue::Number(l * r))
-			}
-			(_, LoxValue::Number(_)) => Err(Error::IncompatibleTypes(
-				op.line,
-				expr.to_owned(),
-				right.lox_type(),
-				left.lox_type(),
-			)),
-			(LoxValue::Number(_), _) => Err(Error::IncompatibleTypes(
-				op.line,
-				expr.to_owned(),
-				left.lox_type(),
-				right.lox_type(),
-			)),
-			_ => Err(Error::InoperableTypes(
-				op.to_owned(),
-				expr.to_owned(),
-				vec![LoxType::Number],
-				left.lox_type(),
-				right.lox_type(),
-			)),
-		},
-		TokenType::Slash => match (&left, &right) {
-			(LoxValue::Number(l), LoxValue::Number(r)) => {
-				if *r == 0_f64 {
-					return Err(Error::RuntimeError(
-						op.line,
-						expr.to_owned(),
-						"Divide by zero".to_string(),
-					));
-				}
-				Ok(LoxValue::Number(l / r))
-			}
-			(_, LoxValue::Number(_)) => Err(Error::IncompatibleTypes(
-				op.line,
-				expr.to_owned(),
-				right.lox_type(),
-				left.lox_type(),
-			)),
-			(LoxValue::Number(_), _) => Err(Error::IncompatibleTypes(
-				op.line,
-				expr.to_owned(),
-				left.lox_type(),
-				right.lox_type(),
-			)),
-			_ => Err(Error::InoperableTypes(
-				op.to_owned(),
-				expr.to_owned(),
-				vec![LoxType::Number],
-				left.lox_type(),
-				right.lox_type(),
-			)),
-		},
+		TokenType::Star => arithmetic(
+			expr,
+			&left,
+			&op,
+			&right,
+			i64::checked_mul,
+			|l, r| l * r,
+		),
+		// Integer division truncates towards zero.
+		TokenType::Slash => arithmetic(
+			expr,
+			&left,
+			&op,
+			&right,
+			i64::checked_div,
+			|l, r| l / r,
+		),
+		// The remainder takes the sign of the dividend.
+		TokenType::Percent => arithmetic(
+			expr,
+			&left,
+			&op,
+			&right,
+			i64::checked_rem,
+			|l, r| l % r,
+		),
 		_ => unreachable!("Factor operator: {:?}", op.token_type),
 	}
 }
@@ -214,65 +266,38 @@ fn visit_term_expression(
 	right: LoxValue,
 ) -> Result<LoxValue, Error> {
 	match op.token_type.clone() {
-		TokenType::Minus => match (&left, &right) {
-			(LoxValue::Number(l), LoxValue::Number(r)) => {
-				Ok(LoxValue::Number(l - r))
-			}
-			(_, LoxValue::Number(_)) => Err(Error::IncompatibleTypes(
-				op.line,
-				expr.to_owned(),
-				right.lox_type(),
-				left.lox_type(),
-			)),
-			(LoxValue::Number(_), _) => Err(Error::IncompatibleTypes(
-				op.line,
-				expr.to_owned(),
-				left.lox_type(),
-				right.lox_type(),
-			)),
-			_ => Err(Error::InoperableTypes(
-				op.to_owned(),
-				expr.to_owned(),
-				vec![LoxType::Number],
-				left.lox_type(),
-				right.lox_type(),
-			)),
-		},
+		TokenType::Minus => arithmetic(
+			expr,
+			&left,
+			&op,
+			&right,
+			i64::checked_sub,
+			|l, r| l - r,
+		),
 		TokenType::Plus => match (&left, &right) {
-			(LoxValue::Number(l), LoxValue::Number(r)) => {
-				Ok(LoxValue::Number(l + r))
-			}
 			(LoxValue::String(l), LoxValue::String(r)) => {
 				Ok(LoxValue::String(l.to_owned() + r.as_str()))
 			}
-			(LoxValue::Number(_), _) => Err(Error::IncompatibleTypes(
-				op.line,
-				expr.to_owned(),
-				left.lox_type(),
-				right.lox_type(),
-			)),
-			(_, LoxValue::Number(_)) => Err(Error::IncompatibleTypes(
-				op.line,
-				expr.to_owned(),
-				right.lox_type(),
-				left.lox_type(),
-			)),
-			(LoxValue::String(_), _) => Err(Error::IncompatibleTypes(
-				op.line,
-				expr.to_owned(),
-				left.lox_type(),
-				right.lox_type(),
-			)),
-			(_, LoxValue::String(_)) => Err(Error::IncompatibleTypes(
-				op.line,
-				expr.to_owned(),
-				right.lox_type(),
-				left.lox_type(),
-			)),
+			(LoxValue::String(_), _) | (_, LoxValue::String(_)) => {
+				Err(Error::IncompatibleTypes(
+					op.span.clone(),
+					expr.to_owned(),
+					left.lox_type(),
+					right.lox_type(),
+				))
+			}
+			_ if left.is_number() || right.is_number() => arithmetic(
+				expr,
+				&left,
+				&op,
+				&right,
+				i64::checked_add,
+				|l, r| l + r,
+			),
 			_ => Err(Error::InoperableTypes(
-				op.to_owned(),
+				op.span.clone(),
 				expr.to_owned(),
-				vec![LoxType::Number, LoxType::String],
+				vec![LoxType::Int, LoxType::Float, LoxType::String],
 				left.lox_type(),
 				right.lox_type(),
 			)),
@@ -280,3 +305,76 @@ fn visit_term_expression(
 		_ => unreachable!("Term operator: {:?}", op.token_type),
 	}
 }
+
+fn visit_exponent_expression(
+	expr: &Expr,
+	left: LoxValue,
+	op: Token,
+	right: LoxValue,
+) -> Result<LoxValue, Error> {
+	match (&left, &right) {
+		// An int raised to a negative power is generally not an int, so
+		// gives a float.
+		(LoxValue::Int(l), LoxValue::Int(r)) if *r >= 0 => u32::try_from(*r)
+			.ok()
+			.and_then(|r| l.checked_pow(r))
+			.map(LoxValue::Int)
+			.ok_or_else(|| {
+				Error::RuntimeError(
+					op.span.clone(),
+					expr.to_owned(),
+					"Integer overflow".to_string(),
+				)
+			}),
+		(LoxValue::Int(l), LoxValue::Int(r)) => {
+			Ok(LoxValue::Float((*l as f64).powf(*r as f64)))
+		}
+		_ => arithmetic(
+			expr,
+			&left,
+			&op,
+			&right,
+			|_, _| unreachable!("Ints are raised to powers above"),
+			f64::powf,
+		),
+	}
+}
+
+fn visit_bitwise_expression(
+	expr: &Expr,
+	left: LoxValue,
+	op: Token,
+	right: LoxValue,
+) -> Result<LoxValue, Error> {
+	let (LoxValue::Int(l), LoxValue::Int(r)) = (&left, &right) else {
+		return Err(Error::InoperableTypes(
+			op.span.clone(),
+			expr.to_owned(),
+			vec![LoxType::Int],
+			left.lox_type(),
+			right.lox_type(),
+		));
+	};
+	let shift = |shift: fn(i64, u32) -> Option<i64>| {
+		u32::try_from(*r)
+			.ok()
+			.and_then(|r| shift(*l, r))
+			.map(LoxValue::Int)
+			.ok_or_else(|| {
+				Error::RuntimeError(
+					op.span.clone(),
+					expr.to_owned(),
+					format!("Cannot shift by {r} bits"),
+				)
+			})
+	};
+	match op.token_type.clone() {
+		TokenType::Ampersand => Ok(LoxValue::Int(l & r)),
+		TokenType::Pipe => Ok(LoxValue::Int(l | r)),
+		TokenType::Caret => Ok(LoxValue::Int(l ^ r)),
+		TokenType::LessLess => shift(i64::checked_shl),
+		// Shifting right keeps the sign.
+		TokenType::GreaterGreater => shift(i64::checked_shr),
+		_ => unreachable!("Bitwise operator: {:?}", op.token_type),
+	}
+}
@@ -1,37 +1,148 @@
-use super::{error::Error, token::Token};
+use std::cell::Cell;
+use std::rc::Rc;
+
+use super::{error::Error, span::Span, token::Token};
 
 mod binary;
 
 pub use binary::*;
 
+// Nodes whose own tokens do not reach both ends of their source, such as
+// statements ending in a `;` that is not kept, carry their span instead.
 generate_ast! [
     {
         Expr {
-            Assign(Token, Box<Expr>),
+            /// An assignment, with the operator a compound assignment such as
+            /// `+=` applies to the current and assigned values.
+            Assign(Token, Option<Box<Token>>, Box<Expr>, Depth),
             Logical(Box<Expr>, Token, Box<Expr>),
+            /// `condition ? then : otherwise`, with the `?` token.
+            Ternary(Box<Expr>, Token, Box<Expr>, Box<Expr>),
             Binary(Box<Expr>, Token, Box<Expr>),
-            Grouping(Box<Expr>),
+            Grouping(Span, Box<Expr>),
             Literal(Token),
+            /// A string with interpolated expressions, made of literal parts
+            /// alternating with the expressions, starting and ending with a
+            /// literal part.
+            Interpolation(Token, Vec<Expr>),
             Unary(Token, Box<Expr>),
-            Identifier(Token),
+            Identifier(Token, Depth),
+            Call(Box<Expr>, Token, Vec<Expr>),
+            Lambda(Rc<Function>),
+            Get(Box<Expr>, Token),
+            Set(Box<Expr>, Token, Option<Box<Token>>, Box<Expr>),
+            This(Token, Depth),
+            Super(Token, Token, Depth),
+            List(Span, Vec<Expr>),
+            Map(Span, Vec<(Expr, Expr)>),
+            /// `list[index]`, with the closing `]` token.
+            Index(Box<Expr>, Token, Box<Expr>),
+            SetIndex(Box<Expr>, Token, Box<Expr>, Option<Box<Token>>, Box<Expr>),
         }
     },
     {
         Stmt {
-            Expression(Expr),
+            Expression(Span, Expr),
             If(Token, Expr, Box<Stmt>, Option<Box<Stmt>>),
-            Print(Expr),
-            Block(Vec<Decl>),
+            Print(Span, Expr),
+            Block(Span, Vec<Decl>),
+            While(Token, Expr, Box<Stmt>),
+            For(Token, Option<Box<Decl>>, Option<Expr>, Option<Expr>, Box<Stmt>),
+            Break(Span, Token),
+            Continue(Span, Token),
+            Return(Span, Token, Option<Expr>),
         }
     },
     {
         Decl {
-            Declaration(Token, Option<Expr>),
+            Declaration(Span, Token, Option<Expr>),
+            Function(Rc<Function>),
+            Class(Span, Token, Option<Expr>, Vec<Rc<Function>>),
             Statement(Stmt),
         }
     }
 ];
 
+ast! {
+    /// A named function declaration, or an anonymous function when `name` is
+    /// `None`. Methods have no `keyword`. Shared between the AST and every
+    /// closure created from it.
+    pub struct Function {
+        pub span: Span,
+        pub keyword: Option<Token>,
+        pub name: Option<Token>,
+        pub params: Vec<Token>,
+        pub body: Vec<Decl>,
+    }
+}
+
+impl Expr {
+    /// The range of source the expression was parsed from.
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Assign(name, _, value, _) => name.span.to(&value.span()),
+            Self::Logical(left, _, right) | Self::Binary(left, _, right) => left.span().to(&right.span()),
+            Self::Ternary(condition, _, _, otherwise) => condition.span().to(&otherwise.span()),
+            Self::Grouping(span, _) | Self::List(span, _) | Self::Map(span, _) => span.clone(),
+            Self::Literal(tok) | Self::Identifier(tok, _) | Self::This(tok, _) => tok.span.clone(),
+            Self::Interpolation(start, parts) => match parts.last() {
+                Some(end) => start.span.to(&end.span()),
+                None => start.span.clone(),
+            },
+            Self::Unary(op, right) => op.span.to(&right.span()),
+            Self::Call(callee, paren, _) => callee.span().to(&paren.span),
+            Self::Lambda(function) => function.span.clone(),
+            Self::Get(object, name) => object.span().to(&name.span),
+            Self::Set(object, _, _, value) => object.span().to(&value.span()),
+            Self::Super(keyword, method, _) => keyword.span.to(&method.span),
+            Self::Index(list, bracket, _) => list.span().to(&bracket.span),
+            Self::SetIndex(list, _, _, _, value) => list.span().to(&value.span()),
+        }
+    }
+}
+
+impl Stmt {
+    /// The range of source the statement was parsed from.
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Expression(span, _)
+            | Self::Print(span, _)
+            | Self::Block(span, _)
+            | Self::Break(span, _)
+            | Self::Continue(span, _)
+            | Self::Return(span, ..) => span.clone(),
+            Self::If(keyword, _, then, otherwise) => keyword.span.to(&otherwise.as_deref().unwrap_or(then).span()),
+            Self::While(keyword, _, body) | Self::For(keyword, .., body) => keyword.span.to(&body.span()),
+        }
+    }
+}
+
+impl Decl {
+    /// The range of source the declaration was parsed from.
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Declaration(span, ..) | Self::Class(span, ..) => span.clone(),
+            Self::Function(function) => function.span.clone(),
+            Self::Statement(stmt) => stmt.span(),
+        }
+    }
+}
+
+/// How many scopes out from the innermost one a variable reference resolves
+/// to, as computed by the resolver. Unresolved references are globals.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Depth(Cell<Option<usize>>);
+
+impl Depth {
+    pub fn get(&self) -> Option<usize> {
+        self.0.get()
+    }
+
+    pub fn set(&self, depth: usize) {
+        self.0.set(Some(depth));
+    }
+}
+
 pub trait Visitor<R, E> {
     fn visit(&mut self, expr: &E) -> Result<R, Error>;
 }
@@ -39,22 +150,69 @@ pub trait Visitor<R, E> {
 #[derive(Default)]
 pub struct AstPrinter;
 
+impl AstPrinter {
+    fn function(&mut self, function: &Function) -> Result<String, Error> {
+        let keyword = function.keyword.iter().map(|k| k.lexeme.clone());
+        let name = function.name.iter().map(|n| n.lexeme.clone());
+        let signature = keyword.chain(name).collect::<Vec<String>>().join(" ");
+        let params = function
+            .params
+            .iter()
+            .map(|p| p.lexeme.clone())
+            .collect::<Vec<String>>()
+            .join(", ");
+        let body = self.block(&function.body)?;
+        Ok(format!("{signature}({params}) {body}"))
+    }
+
+    fn block(&mut self, decls: &[Decl]) -> Result<String, Error> {
+        let block = decls
+            .iter()
+            .map(|decl| {
+                let decl = decl.accept(self).unwrap();
+                let decl = decl
+                    .lines()
+                    .map(|l| format!("  {l}"))
+                    .collect::<Vec<String>>();
+                decl.join("\n")
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        Ok(format!("{{\n{block}\n}}"))
+    }
+}
+
 impl Visitor<String, Decl> for AstPrinter {
     fn visit(&mut self, expr: &Decl) -> Result<String, Error> {
         match expr {
-            Decl::Declaration(token, Some(e)) => {
+            Decl::Declaration(_, token, Some(e)) => {
                 let e = e.accept(self).unwrap();
                 let ident = token.clone().lexeme;
                 Ok(format!("var {ident} = {e};"))
             }
-            Decl::Declaration(token, None) => {
+            Decl::Declaration(_, token, None) => {
                 let ident = token.clone().lexeme;
                 Ok(format!("var {ident};"))
             }
-            Decl::Statement(s) => {
-                let s = s.accept(self)?;
-                Ok(format!("print {s}"))
+            Decl::Function(f) => self.function(f),
+            Decl::Class(_, name, superclass, methods) => {
+                let superclass = match superclass {
+                    Some(superclass) => format!(" < {}", superclass.accept(self)?),
+                    None => String::default(),
+                };
+                let methods = methods
+                    .iter()
+                    .map(|m| self.function(m))
+                    .collect::<Result<Vec<String>, Error>>()?
+                    .join("\n");
+                let methods = methods
+                    .lines()
+                    .map(|l| format!("  {l}"))
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                Ok(format!("class {}{superclass} {{\n{methods}\n}}", name.lexeme))
             }
+            Decl::Statement(s) => s.accept(self),
         }
     }
 }
@@ -62,7 +220,7 @@ impl Visitor<String, Decl> for AstPrinter {
 impl Visitor<String, Stmt> for AstPrinter {
     fn visit(&mut self, expr: &Stmt) -> Result<String, Error> {
         match expr {
-            Stmt::Expression(e) => e.accept(self),
+            Stmt::Expression(_, e) => e.accept(self),
             Stmt::If(_, ie, s, ee) => {
                 let ie = ie.accept(self)?;
                 let s = s.accept(self)?;
@@ -72,26 +230,36 @@ impl Visitor<String, Stmt> for AstPrinter {
                 };
                 Ok(format!("if ({ie}) {s}{ee}"))
             }
-            Stmt::Print(e) => {
+            Stmt::Print(_, e) => {
                 let e = e.accept(self)?;
                 Ok(format!("print {e}"))
             }
-            Stmt::Block(decls) => {
-                let block = decls
-                    .iter()
-                    .map(|decl| {
-                        let decl = decl.accept(self).unwrap();
-                        let decl = decl
-                            .lines()
-                            .map(|l| format!("  {l}"))
-                            .collect::<Vec<String>>();
-                        let decl = decl.join("\n");
-                        decl
-                    })
-                    .collect::<Vec<String>>()
-                    .join("\n");
-                Ok(format!("{{\n{block}\n}}"))
+            Stmt::Block(_, decls) => self.block(decls),
+            Stmt::While(_, cond, body) => {
+                let cond = cond.accept(self)?;
+                let body = body.accept(self)?;
+                Ok(format!("while ({cond}) {body}"))
             }
+            Stmt::For(_, init, cond, incr, body) => {
+                let init = match init {
+                    Some(init) => init.accept(self)?,
+                    None => ";".to_string(),
+                };
+                let cond = match cond {
+                    Some(cond) => format!(" {}", cond.accept(self)?),
+                    None => String::default(),
+                };
+                let incr = match incr {
+                    Some(incr) => format!(" {}", incr.accept(self)?),
+                    None => String::default(),
+                };
+                let body = body.accept(self)?;
+                Ok(format!("for ({init}{cond};{incr}) {body}"))
+            }
+            Stmt::Break(..) => Ok("break".to_string()),
+            Stmt::Continue(..) => Ok("continue".to_string()),
+            Stmt::Return(_, _, Some(e)) => Ok(format!("return {}", e.accept(self)?)),
+            Stmt::Return(_, _, None) => Ok("return".to_string()),
         }
     }
 }
@@ -99,10 +267,17 @@ impl Visitor<String, Stmt> for AstPrinter {
 impl Visitor<String, Expr> for AstPrinter {
     fn visit(&mut self, expr: &Expr) -> Result<String, Error> {
         let expr = match expr {
-            Expr::Assign(ident, sub_expr) => {
+            Expr::Assign(ident, op, sub_expr, _) => {
                 let ident = &ident.lexeme;
+                let op = assignment_operator(op.as_deref());
                 let sub_expr = sub_expr.accept(self).unwrap();
-                format!("{ident} = {sub_expr}")
+                format!("{ident} {op} {sub_expr}")
+            }
+            Expr::Ternary(condition, _, then, otherwise) => {
+                let condition = condition.accept(self).unwrap();
+                let then = then.accept(self).unwrap();
+                let otherwise = otherwise.accept(self).unwrap();
+                format!("{condition} ? {then} : {otherwise}")
             }
             Expr::Logical(left, op, right) => {
                 let op = &op.lexeme;
@@ -116,23 +291,87 @@ impl Visitor<String, Expr> for AstPrinter {
                 let right = right.accept(self).unwrap();
                 format!("{left} {op} {right}")
             }
-            Expr::Grouping(sub_expr) => {
+            Expr::Grouping(_, sub_expr) => {
                 let sub_expr = sub_expr.accept(self).unwrap();
                 format!("({sub_expr})")
             }
             Expr::Literal(lit) => lit.lexeme.clone(),
+            // The literal parts keep the source around the expressions.
+            Expr::Interpolation(_, parts) => parts
+                .iter()
+                .map(|part| part.accept(self).unwrap())
+                .collect::<Vec<String>>()
+                .concat(),
             Expr::Unary(op, sub_expr) => {
                 let op = &op.lexeme;
                 let sub_expr = sub_expr.accept(self).unwrap();
                 format!("{op}{sub_expr}")
             }
-            Expr::Identifier(ident) => ident.lexeme.clone(),
+            Expr::Identifier(ident, _) => ident.lexeme.clone(),
+            Expr::Call(callee, _, args) => {
+                let callee = callee.accept(self).unwrap();
+                let args = args
+                    .iter()
+                    .map(|a| a.accept(self).unwrap())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                format!("{callee}({args})")
+            }
+            Expr::Lambda(f) => self.function(f)?,
+            Expr::Get(object, name) => {
+                let object = object.accept(self).unwrap();
+                format!("{object}.{}", name.lexeme)
+            }
+            Expr::Set(object, name, op, value) => {
+                let object = object.accept(self).unwrap();
+                let op = assignment_operator(op.as_deref());
+                let value = value.accept(self).unwrap();
+                format!("{object}.{} {op} {value}", name.lexeme)
+            }
+            Expr::This(keyword, _) => keyword.lexeme.clone(),
+            Expr::Super(keyword, method, _) => format!("{}.{}", keyword.lexeme, method.lexeme),
+            Expr::List(_, items) => {
+                let items = items
+                    .iter()
+                    .map(|i| i.accept(self).unwrap())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                format!("[{items}]")
+            }
+            Expr::Map(_, entries) => {
+                let entries = entries
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", k.accept(self).unwrap(), v.accept(self).unwrap()))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                format!("{{{entries}}}")
+            }
+            Expr::Index(list, _, index) => {
+                let list = list.accept(self).unwrap();
+                let index = index.accept(self).unwrap();
+                format!("{list}[{index}]")
+            }
+            Expr::SetIndex(list, _, index, op, value) => {
+                let list = list.accept(self).unwrap();
+                let index = index.accept(self).unwrap();
+                let op = assignment_operator(op.as_deref());
+                let value = value.accept(self).unwrap();
+                format!("{list}[{index}] {op} {value}")
+            }
         };
 
         Ok(expr)
     }
 }
 
+/// `=`, or the compound assignment applying the operator `op`.
+fn assignment_operator(op: Option<&Token>) -> String {
+    match op {
+        Some(op) => format!("{}=", op.lexeme),
+        None => "=".to_string(),
+    }
+}
+
 macro_rules! generate_ast {
     [$($def:tt),*] => {
         $(
@@ -0,0 +1,621 @@
+use std::collections::HashSet;
+
+use super::{
+    error::Error,
+    expression::AstPrinter,
+    span::Span,
+    syntax::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken, SyntaxTree},
+    token::TokenType,
+};
+
+/// The width formatted lines are kept within where they can be broken.
+const WIDTH: usize = 80;
+
+/// How many spaces each level of nesting is indented by.
+const INDENT: usize = 2;
+
+/// Reformats `source`, read from `file`, in the canonical style, keeping its
+/// comments.
+///
+/// Only whitespace is changed, so the formatted source parses to the same
+/// declarations, and formatting it again changes nothing.
+///
+/// ```
+/// let source = "var  total=1+2 ;// three\nif(total>2){print total;}";
+/// assert_eq!(
+///     rlox::format(source, "total.lox").unwrap(),
+///     "var total = 1 + 2; // three\nif (total > 2) {\n  print total;\n}\n"
+/// );
+/// ```
+pub fn format(source: &str, file: &str) -> Result<String, Vec<Error>> {
+    let tree = SyntaxTree::parse(source, file);
+    if !tree.errors().is_empty() {
+        return Err(tree.errors().to_vec());
+    }
+    let doc = Formatter::default().script(tree.root());
+    let formatted = Printer::default().print(&doc);
+
+    // Tokens could lex differently once the whitespace between them has
+    // changed, so the formatted source is checked before it is handed back.
+    let reformatted = SyntaxTree::parse(&formatted, file);
+    match reformatted.errors().is_empty() && printed(&tree) == printed(&reformatted) {
+        true => Ok(formatted),
+        false => Err(vec![Error::Unformattable(Span {
+            file: file.into(),
+            line: 1,
+            column: 1,
+            end_line: 1,
+            end_column: 1,
+            ..Span::default()
+        })]),
+    }
+}
+
+/// The declarations of `tree`, as printed by [`AstPrinter`].
+fn printed(tree: &SyntaxTree) -> Option<Vec<String>> {
+    tree.decls().iter().map(|decl| decl.accept(&mut AstPrinter).ok()).collect()
+}
+
+/// A layout of source, whose groups are broken over several lines only when
+/// they do not fit on one.
+enum Doc {
+    Text(String),
+    /// A space, or a line break in a broken group.
+    Line,
+    /// Nothing, or a line break in a broken group.
+    SoftLine,
+    HardLine,
+    /// Puts whatever follows on a new line, as after a line comment.
+    EndLine,
+    /// Text only written in a broken group, such as a trailing comma.
+    IfBroken(&'static str),
+    Concat(Vec<Doc>),
+    /// Indents the lines broken within a broken group.
+    Indent(Vec<Doc>),
+    /// A group, and whether something in it forces it to be broken.
+    Group(Vec<Doc>, bool),
+    /// A group that is always broken, such as a block, without forcing the
+    /// groups around it to be.
+    Broken(Vec<Doc>),
+}
+
+impl Doc {
+    fn text(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+
+    fn group(docs: Vec<Doc>) -> Self {
+        let broken = docs.iter().any(Doc::breaks);
+        Self::Group(docs, broken)
+    }
+
+    /// Whether the doc forces the group it is in to be broken.
+    fn breaks(&self) -> bool {
+        match self {
+            Self::HardLine | Self::EndLine => true,
+            Self::Concat(docs) | Self::Indent(docs) => docs.iter().any(Doc::breaks),
+            Self::Group(_, broken) => *broken,
+            _ => false,
+        }
+    }
+}
+
+/// Lays out the nodes of a syntax tree.
+#[derive(Default)]
+struct Formatter {
+    /// The offsets of tokens whose leading comments have already been laid
+    /// out, on lines of their own.
+    placed: HashSet<usize>,
+}
+
+impl Formatter {
+    fn script(&mut self, root: &SyntaxNode) -> Doc {
+        let elements = root.children().collect::<Vec<SyntaxElement>>();
+        let Some((SyntaxElement::Token(eof), declarations)) = elements.split_last() else {
+            unreachable!("a script ends with its `Eof` token")
+        };
+        let mut docs = self.lines(declarations, eof);
+        if !docs.is_empty() {
+            docs.push(Doc::HardLine);
+        }
+        Doc::Concat(docs)
+    }
+
+    /// Lays out declarations one per line, followed by the comments before
+    /// `close`, which ends them.
+    ///
+    /// Blank lines between declarations or comments are kept, though runs
+    /// of them are collapsed into one.
+    fn lines(&mut self, elements: &[SyntaxElement], close: &SyntaxToken) -> Vec<Doc> {
+        let mut docs = vec![];
+        for element in elements {
+            match element {
+                SyntaxElement::Node(node) => {
+                    let first = node.tokens().remove(0);
+                    if !docs.is_empty() {
+                        docs.push(Doc::HardLine);
+                    }
+                    let (comments, blank) = self.comments(&first, docs.is_empty());
+                    docs.extend(comments);
+                    if blank {
+                        docs.push(Doc::HardLine);
+                    }
+                    docs.push(self.node(node));
+                }
+                // A `;` after a statement that does not need one.
+                SyntaxElement::Token(token) => docs.push(self.token(token)),
+            }
+        }
+
+        let (mut comments, _) = self.comments(close, docs.is_empty());
+        // `close` goes on a line of its own after the last comment.
+        comments.pop();
+        if !docs.is_empty() && !comments.is_empty() {
+            docs.push(Doc::HardLine);
+        }
+        docs.extend(comments);
+        docs
+    }
+
+    /// Lays out the comments before `token`, which starts a line, on lines
+    /// of their own, returning them along with whether a blank line is left
+    /// between them and the token.
+    fn comments(&mut self, token: &SyntaxToken, first: bool) -> (Vec<Doc>, bool) {
+        self.placed.insert(token.range().start);
+        let mut docs = vec![];
+        // The line break ending the line before is trailing trivia of the
+        // token on it, so a single line break here leaves a blank line.
+        let mut newlines = 1;
+        let mut ends_line = false;
+        // What follows a comment, which is on the same line only for a block
+        // comment that was.
+        let separator = |newlines: usize, ends_line: bool| match newlines > 0 || ends_line {
+            true => Doc::HardLine,
+            false => Doc::text(" "),
+        };
+        for trivia in token.leading() {
+            match trivia.kind {
+                TokenType::Whitespace => newlines += trivia.text.matches('\n').count(),
+                _ => {
+                    if !docs.is_empty() {
+                        docs.push(separator(newlines, ends_line));
+                    }
+                    if newlines >= 2 && !(first && docs.is_empty()) {
+                        docs.push(Doc::HardLine);
+                    }
+                    docs.push(Doc::text(&trivia.text));
+                    newlines = 0;
+                    ends_line = trivia.kind != TokenType::BlockComment;
+                }
+            }
+        }
+        if !docs.is_empty() {
+            docs.push(separator(newlines, ends_line));
+        }
+        let blank = newlines >= 2 && !(first && docs.is_empty());
+        (docs, blank)
+    }
+
+    fn token(&mut self, token: &SyntaxToken) -> Doc {
+        self.around(token, Doc::text(token.text()))
+    }
+
+    /// Lays out `doc` in place of `token`, along with the comments around
+    /// the token.
+    fn around(&mut self, token: &SyntaxToken, doc: Doc) -> Doc {
+        let mut docs = vec![];
+        if !self.placed.contains(&token.range().start) {
+            let mut newline = false;
+            let mut comments = false;
+            for trivia in token.leading() {
+                match trivia.kind {
+                    TokenType::Whitespace => newline |= trivia.text.contains('\n'),
+                    _ => {
+                        docs.push(match (newline, comments) {
+                            (true, _) => Doc::EndLine,
+                            (false, true) => Doc::text(" "),
+                            (false, false) => Doc::Concat(vec![]),
+                        });
+                        docs.push(Doc::text(&trivia.text));
+                        comments = true;
+                        newline = trivia.kind != TokenType::BlockComment;
+                    }
+                }
+            }
+            match (newline, comments) {
+                (true, true) => docs.push(Doc::EndLine),
+                (false, true) => docs.push(Doc::text(" ")),
+                _ => {}
+            }
+        }
+        docs.push(doc);
+        for trivia in token.trailing() {
+            if trivia.kind != TokenType::Whitespace {
+                docs.push(Doc::text(" "));
+                docs.push(Doc::text(&trivia.text));
+                if trivia.kind != TokenType::BlockComment {
+                    docs.push(Doc::EndLine);
+                }
+            }
+        }
+        Doc::Concat(docs)
+    }
+
+    fn node(&mut self, node: &SyntaxNode) -> Doc {
+        use SyntaxKind::*;
+        let elements = node.children().collect::<Vec<SyntaxElement>>();
+        match node.kind() {
+            Script => self.script(node),
+            Block => self.block(&elements),
+            ClassDecl => {
+                let mut docs = vec![];
+                for (i, element) in elements.iter().enumerate() {
+                    match element {
+                        SyntaxElement::Token(token) if token.kind() == &TokenType::LeftBrace => {
+                            docs.push(Doc::text(" "));
+                            docs.push(self.block(&elements[i..]));
+                            break;
+                        }
+                        SyntaxElement::Token(token) if token.kind() == &TokenType::Class => {
+                            docs.push(self.token(token));
+                        }
+                        element => {
+                            docs.push(Doc::text(" "));
+                            docs.push(self.element(element));
+                        }
+                    }
+                }
+                Doc::Concat(docs)
+            }
+            FunctionDecl | LambdaExpr => Doc::Concat(
+                elements
+                    .iter()
+                    .map(|element| match element {
+                        SyntaxElement::Token(token) if [TokenType::Fun, TokenType::Fn].contains(token.kind()) => {
+                            Doc::Concat(vec![self.token(token), Doc::text(" ")])
+                        }
+                        SyntaxElement::Node(node) if node.kind() == Block => {
+                            Doc::Concat(vec![Doc::text(" "), self.node(node)])
+                        }
+                        element => self.element(element),
+                    })
+                    .collect(),
+            ),
+            ParamList => self.delimited(&elements, false),
+            CallExpr => {
+                let (callee, args) = elements.split_first().unwrap();
+                Doc::Concat(vec![self.element(callee), self.delimited(args, true)])
+            }
+            ListExpr | MapExpr => self.delimited(&elements, true),
+            IfStmt => {
+                let mut docs = vec![];
+                let mut condition = true;
+                let mut block = false;
+                for element in elements.iter() {
+                    match element {
+                        SyntaxElement::Token(token) if token.kind() == &TokenType::If => {
+                            docs.push(self.token(token));
+                            docs.push(Doc::text(" "));
+                        }
+                        SyntaxElement::Token(token) if token.kind() == &TokenType::RightParen && condition => {
+                            docs.push(self.token(token));
+                            condition = false;
+                        }
+                        // `else` follows the `}` of a block, and otherwise
+                        // starts a line.
+                        SyntaxElement::Token(token) if token.kind() == &TokenType::Else => {
+                            docs.push(if block { Doc::text(" ") } else { Doc::HardLine });
+                            docs.push(self.token(token));
+                        }
+                        SyntaxElement::Node(node) if !condition => {
+                            block = node.kind() == Block;
+                            match node.kind() {
+                                IfStmt => docs.extend([Doc::text(" "), self.node(node)]),
+                                _ => docs.push(self.body(node)),
+                            }
+                        }
+                        element => docs.push(self.element(element)),
+                    }
+                }
+                Doc::Concat(docs)
+            }
+            WhileStmt | ForStmt => {
+                let mut docs = vec![];
+                let (body, header) = elements.split_last().unwrap();
+                for (i, element) in header.iter().enumerate() {
+                    docs.push(self.element(element));
+                    // Between the keyword and the `(`, and after each `;` of
+                    // a `for` unless nothing else follows it.
+                    let ends_clause = match element {
+                        SyntaxElement::Token(token) => token.kind() == &TokenType::SemiColon,
+                        SyntaxElement::Node(node) => [VarDecl, ExprStmt].contains(&node.kind()),
+                    };
+                    let next = match header.get(i + 1) {
+                        Some(SyntaxElement::Token(token)) => Some(token.kind()),
+                        _ => None,
+                    };
+                    if i == 0 || ends_clause && ![Some(&TokenType::SemiColon), Some(&TokenType::RightParen)].contains(&next) {
+                        docs.push(Doc::text(" "));
+                    }
+                }
+                let SyntaxElement::Node(body) = body else {
+                    unreachable!("a loop ends with its body")
+                };
+                docs.push(self.body(body));
+                Doc::Concat(docs)
+            }
+            // Words and operators separated by spaces, up to a closing `;`.
+            VarDecl | ExprStmt | PrintStmt | ReturnStmt | BreakStmt | ContinueStmt | AssignExpr => {
+                let mut docs = vec![];
+                for (i, element) in elements.iter().enumerate() {
+                    let semicolon = matches!(element, SyntaxElement::Token(token) if token.kind() == &TokenType::SemiColon);
+                    if i > 0 && !semicolon {
+                        docs.push(Doc::text(" "));
+                    }
+                    docs.push(self.element(element));
+                }
+                Doc::Concat(docs)
+            }
+            TernaryExpr => {
+                let (condition, rest) = elements.split_first().unwrap();
+                let mut branches = vec![];
+                for element in rest {
+                    match element {
+                        SyntaxElement::Token(token) => {
+                            branches.push(Doc::Line);
+                            branches.push(self.token(token));
+                            branches.push(Doc::text(" "));
+                        }
+                        element => branches.push(self.element(element)),
+                    }
+                }
+                Doc::group(vec![self.element(condition), Doc::Indent(branches)])
+            }
+            BinaryExpr => {
+                // `a + b + c` is laid out as one group, rather than a group
+                // for `a + b` inside another.
+                let mut operands = vec![];
+                let mut node = node.clone();
+                loop {
+                    let mut children = node.children().collect::<Vec<SyntaxElement>>();
+                    let right = children.pop().unwrap();
+                    let op = children.pop().unwrap();
+                    operands.push((op, right));
+                    match children.pop().unwrap() {
+                        SyntaxElement::Node(left) if left.kind() == BinaryExpr => node = left,
+                        left => {
+                            operands.push((left.clone(), left));
+                            break;
+                        }
+                    }
+                }
+                let (_, first) = operands.pop().unwrap();
+                let mut rest = vec![];
+                for (op, right) in operands.iter().rev() {
+                    rest.extend([Doc::text(" "), self.element(op), Doc::Line, self.element(right)]);
+                }
+                Doc::group(vec![self.element(&first), Doc::Indent(rest)])
+            }
+            // `- -a` would read as `--a` without the space.
+            UnaryExpr => {
+                let nested_minus = |element: &SyntaxElement| match element {
+                    SyntaxElement::Node(node) => {
+                        node.kind() == UnaryExpr
+                            && node.child_tokens().next().is_some_and(|op| op.kind() == &TokenType::Minus)
+                    }
+                    SyntaxElement::Token(token) => token.kind() == &TokenType::Minus,
+                };
+                let mut docs = vec![];
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 && nested_minus(&elements[i - 1]) && nested_minus(element) {
+                        docs.push(Doc::text(" "));
+                    }
+                    docs.push(self.element(element));
+                }
+                Doc::Concat(docs)
+            }
+            // Tokens and operands in a row, without spaces between them.
+            GetExpr | IndexExpr | Literal | Name | ThisExpr | SuperExpr | GroupingExpr
+            | InterpolationExpr | Error => Doc::Concat(elements.iter().map(|element| self.element(element)).collect()),
+        }
+    }
+
+    fn element(&mut self, element: &SyntaxElement) -> Doc {
+        match element {
+            SyntaxElement::Node(node) => self.node(node),
+            SyntaxElement::Token(token) => self.token(token),
+        }
+    }
+
+    /// Lays out the body of a block or class, from its `{` to its `}`.
+    fn block(&mut self, elements: &[SyntaxElement]) -> Doc {
+        let [SyntaxElement::Token(open), inner @ .., SyntaxElement::Token(close)] = elements else {
+            unreachable!("a body is enclosed in braces")
+        };
+        let open = self.token(open);
+        let mut lines = self.lines(inner, close);
+        let close = self.token(close);
+        if lines.is_empty() {
+            return Doc::Concat(vec![open, close]);
+        }
+        lines.insert(0, Doc::HardLine);
+        Doc::Broken(vec![open, Doc::Indent(lines), Doc::HardLine, close])
+    }
+
+    /// Lays out the body of an `if` or a loop after its condition, on the
+    /// same line unless it does not fit.
+    fn body(&mut self, node: &SyntaxNode) -> Doc {
+        match node.kind() {
+            SyntaxKind::Block => Doc::Concat(vec![Doc::text(" "), self.node(node)]),
+            _ => Doc::group(vec![Doc::Indent(vec![Doc::Line, self.node(node)])]),
+        }
+    }
+
+    /// Lays out items separated by commas between delimiters, one per line
+    /// if they do not fit on one, with a trailing comma if `trailing` allows
+    /// it.
+    fn delimited(&mut self, elements: &[SyntaxElement], trailing: bool) -> Doc {
+        let [SyntaxElement::Token(open), inner @ .., SyntaxElement::Token(close)] = elements else {
+            unreachable!("items are enclosed in delimiters")
+        };
+        let open = self.token(open);
+        if inner.is_empty() {
+            return Doc::Concat(vec![open, self.token(close)]);
+        }
+
+        let mut items = vec![Doc::SoftLine];
+        let mut comma = None;
+        for element in inner {
+            match element {
+                SyntaxElement::Token(token) if token.kind() == &TokenType::Comma => {
+                    comma = Some(token);
+                }
+                element => {
+                    if let Some(comma) = comma.take() {
+                        items.push(self.token(comma));
+                        items.push(Doc::Line);
+                    }
+                    items.push(self.element(element));
+                    if matches!(element, SyntaxElement::Token(token) if token.kind() == &TokenType::Colon) {
+                        items.push(Doc::text(" "));
+                    }
+                }
+            }
+        }
+        let last = match trailing {
+            true => Doc::IfBroken(","),
+            false => Doc::Concat(vec![]),
+        };
+        items.push(match comma {
+            Some(comma) => self.around(comma, last),
+            None => last,
+        });
+        Doc::group(vec![open, Doc::Indent(items), Doc::SoftLine, self.token(close)])
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+/// Writes out a [`Doc`], breaking the groups that do not fit in [`WIDTH`].
+struct Printer {
+    out: String,
+    column: usize,
+    /// Whether nothing has been written on the current line yet, not even
+    /// its indentation.
+    line_start: bool,
+    /// Whether the next text has to start a new line.
+    end_line: bool,
+}
+
+impl Default for Printer {
+    fn default() -> Self {
+        Self {
+            out: String::new(),
+            column: 0,
+            line_start: true,
+            end_line: false,
+        }
+    }
+}
+
+impl Printer {
+    fn print(mut self, doc: &Doc) -> String {
+        let mut stack = vec![(0, Mode::Break, doc)];
+        while let Some((indent, mode, doc)) = stack.pop() {
+            match doc {
+                Doc::Text(text) => self.text(indent, text),
+                Doc::Line if mode == Mode::Flat && !self.end_line => self.text(indent, " "),
+                Doc::SoftLine if mode == Mode::Flat => {}
+                Doc::Line | Doc::SoftLine | Doc::HardLine => self.newline(),
+                Doc::EndLine => self.end_line = true,
+                Doc::IfBroken(text) => {
+                    if mode == Mode::Break {
+                        self.text(indent, text);
+                    }
+                }
+                Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc))),
+                // A flat group only has lines broken by a line comment before
+                // it, which are indented as if the group were broken.
+                Doc::Indent(docs) => stack.extend(docs.iter().rev().map(|doc| (indent + INDENT, mode, doc))),
+                Doc::Group(docs, broken) => {
+                    let column = if self.line_start { indent } else { self.column };
+                    let mode = match mode {
+                        Mode::Flat => Mode::Flat,
+                        _ if !broken && fits(WIDTH.saturating_sub(column), docs, &stack) => Mode::Flat,
+                        _ => Mode::Break,
+                    };
+                    stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc)));
+                }
+                Doc::Broken(docs) => stack.extend(docs.iter().rev().map(|doc| (indent, Mode::Break, doc))),
+            }
+        }
+        self.out
+    }
+
+    fn text(&mut self, indent: usize, text: &str) {
+        if self.end_line && !self.line_start {
+            self.newline();
+        }
+        self.end_line = false;
+        if self.line_start {
+            // Spacing is not needed at the start of a line.
+            if text == " " {
+                return;
+            }
+            self.out.push_str(&" ".repeat(indent));
+            self.column = indent;
+            self.line_start = false;
+        }
+        self.out.push_str(text);
+        match text.rsplit_once('\n') {
+            Some((_, last)) => self.column = last.chars().count(),
+            None => self.column += text.chars().count(),
+        }
+    }
+
+    fn newline(&mut self) {
+        self.out.truncate(self.out.trim_end_matches(' ').len());
+        self.out.push('\n');
+        self.column = 0;
+        self.line_start = true;
+        self.end_line = false;
+    }
+}
+
+/// Whether `docs` fit in `width` columns laid out flat, along with whatever
+/// follows them in `rest` up to the next line break.
+fn fits(width: usize, docs: &[Doc], rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut width = width as isize;
+    let mut stack = docs.iter().rev().map(|doc| (Mode::Flat, doc)).collect::<Vec<_>>();
+    let mut rest = rest.iter().rev();
+    while width >= 0 {
+        let Some((mode, doc)) = stack.pop().or_else(|| rest.next().map(|(_, mode, doc)| (*mode, *doc))) else {
+            return true;
+        };
+        match doc {
+            Doc::Text(text) => match text.split_once('\n') {
+                Some((first, _)) => return first.chars().count() as isize <= width,
+                None => width -= text.chars().count() as isize,
+            },
+            Doc::Line if mode == Mode::Flat => width -= 1,
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::Line | Doc::SoftLine | Doc::HardLine | Doc::EndLine => return true,
+            Doc::IfBroken(text) => {
+                if mode == Mode::Break {
+                    width -= text.len() as isize;
+                }
+            }
+            Doc::Concat(docs) | Doc::Indent(docs) => stack.extend(docs.iter().rev().map(|doc| (mode, doc))),
+            Doc::Group(docs, broken) => {
+                let mode = if *broken { Mode::Break } else { mode };
+                stack.extend(docs.iter().rev().map(|doc| (mode, doc)));
+            }
+            Doc::Broken(docs) => stack.extend(docs.iter().rev().map(|doc| (Mode::Break, doc))),
+        }
+    }
+    false
+}
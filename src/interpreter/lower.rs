@@ -0,0 +1,244 @@
+use std::rc::Rc;
+
+use super::{
+    expression::{Decl, Depth, Expr, Function, Stmt},
+    span::Span,
+    syntax::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken},
+    token::*,
+};
+
+/// Derives the AST of a script from its syntax tree.
+///
+/// The tree must be free of syntax errors, so that every node holds the
+/// tokens and operands the parser checked its kind calls for.
+pub(crate) struct Lowering<'a> {
+    /// The tokens the tree was parsed from, in source order.
+    tokens: &'a [Token],
+}
+
+impl<'a> Lowering<'a> {
+    pub fn lower(root: &SyntaxNode, tokens: &'a [Token]) -> Vec<Decl> {
+        Self { tokens }.decls(root)
+    }
+
+    /// The declarations directly inside a script or a block.
+    fn decls(&self, node: &SyntaxNode) -> Vec<Decl> {
+        node.child_nodes().map(|decl| self.decl(&decl)).collect()
+    }
+
+    fn decl(&self, node: &SyntaxNode) -> Decl {
+        match node.kind() {
+            SyntaxKind::FunctionDecl => Decl::Function(self.function(node)),
+            SyntaxKind::ClassDecl => {
+                let mut superclass = None;
+                let mut methods = vec![];
+                for child in node.child_nodes() {
+                    match child.kind() {
+                        SyntaxKind::Name => superclass = Some(self.expr(&child)),
+                        _ => methods.push(self.function(&child)),
+                    }
+                }
+                Decl::Class(self.span(node), self.name(node), superclass, methods)
+            }
+            SyntaxKind::VarDecl => {
+                let initializer = node.child_nodes().next().map(|value| self.expr(&value));
+                Decl::Declaration(self.span(node), self.name(node), initializer)
+            }
+            _ => Decl::Statement(self.stmt(node)),
+        }
+    }
+
+    /// Lowers a function declaration, a method or a lambda.
+    fn function(&self, node: &SyntaxNode) -> Rc<Function> {
+        let tokens = self.tokens_in(node);
+        let keyword = tokens.iter().find(|t| matches!(t.token_type, TokenType::Fun | TokenType::Fn)).cloned();
+        let name = tokens.into_iter().find(|t| t.token_type == TokenType::Identifier);
+        let nodes = node.child_nodes().collect::<Vec<_>>();
+        let params = self
+            .tokens_in(&nodes[0])
+            .into_iter()
+            .filter(|t| t.token_type == TokenType::Identifier)
+            .collect();
+
+        Rc::new(Function {
+            span: self.span(node),
+            keyword,
+            name,
+            params,
+            body: self.decls(&nodes[1]),
+        })
+    }
+
+    fn stmt(&self, node: &SyntaxNode) -> Stmt {
+        let span = self.span(node);
+        let keyword = || self.first_token(node);
+        let nodes = node.child_nodes().collect::<Vec<_>>();
+        match node.kind() {
+            SyntaxKind::ExprStmt => Stmt::Expression(span, self.expr(&nodes[0])),
+            SyntaxKind::PrintStmt => Stmt::Print(span, self.expr(&nodes[0])),
+            SyntaxKind::Block => Stmt::Block(span, self.decls(node)),
+            SyntaxKind::IfStmt => Stmt::If(
+                keyword(),
+                self.expr(&nodes[0]),
+                Box::from(self.stmt(&nodes[1])),
+                nodes.get(2).map(|otherwise| Box::from(self.stmt(otherwise))),
+            ),
+            SyntaxKind::WhileStmt => Stmt::While(keyword(), self.expr(&nodes[0]), Box::from(self.stmt(&nodes[1]))),
+            SyntaxKind::ForStmt => self.for_stmt(node),
+            SyntaxKind::BreakStmt => Stmt::Break(span, keyword()),
+            SyntaxKind::ContinueStmt => Stmt::Continue(span, keyword()),
+            SyntaxKind::ReturnStmt => Stmt::Return(span, keyword(), nodes.first().map(|value| self.expr(value))),
+            kind => unreachable!("{kind:?} is not a statement"),
+        }
+    }
+
+    /// Lowers a `for` statement, any of whose clauses may be left out.
+    fn for_stmt(&self, node: &SyntaxNode) -> Stmt {
+        let (mut init, mut cond, mut incr, mut body) = (None, None, None, None);
+        // Which part comes next, as counted by the `;` and `)` ending the
+        // clauses. An initializer is a node ending with its own `;`.
+        let mut part = 0;
+        for child in node.children() {
+            match child {
+                SyntaxElement::Token(token) => {
+                    if matches!(token.kind(), TokenType::SemiColon | TokenType::RightParen) {
+                        part += 1;
+                    }
+                }
+                SyntaxElement::Node(child) => match part {
+                    0 => {
+                        init = Some(Box::from(self.decl(&child)));
+                        part += 1;
+                    }
+                    1 => cond = Some(self.expr(&child)),
+                    2 => incr = Some(self.expr(&child)),
+                    _ => body = Some(self.stmt(&child)),
+                },
+            }
+        }
+        let body = body.expect("a `for` statement has a body");
+
+        Stmt::For(self.first_token(node), init, cond, incr, Box::from(body))
+    }
+
+    fn expr(&self, node: &SyntaxNode) -> Expr {
+        let nodes = node.child_nodes().collect::<Vec<_>>();
+        let operand = |i: usize| Box::from(self.expr(&nodes[i]));
+        let first = || self.first_token(node);
+        let last = || self.last_token(node);
+        match node.kind() {
+            SyntaxKind::AssignExpr => self.assignment(&nodes[0], &first(), operand(1)),
+            SyntaxKind::TernaryExpr => Expr::Ternary(operand(0), first(), operand(1), operand(2)),
+            SyntaxKind::BinaryExpr => {
+                let op = first();
+                match op.token_type {
+                    TokenType::And | TokenType::Or => Expr::Logical(operand(0), op, operand(1)),
+                    _ => Expr::Binary(operand(0), op, operand(1)),
+                }
+            }
+            SyntaxKind::UnaryExpr => Expr::Unary(first(), operand(0)),
+            SyntaxKind::CallExpr => {
+                let args = nodes[1..].iter().map(|arg| self.expr(arg)).collect();
+                Expr::Call(operand(0), last(), args)
+            }
+            SyntaxKind::GetExpr => Expr::Get(operand(0), last()),
+            SyntaxKind::IndexExpr => Expr::Index(operand(0), last(), operand(1)),
+            SyntaxKind::Literal => Expr::Literal(first()),
+            SyntaxKind::Name => Expr::Identifier(first(), Depth::default()),
+            SyntaxKind::ThisExpr => Expr::This(first(), Depth::default()),
+            SyntaxKind::SuperExpr => Expr::Super(first(), last(), Depth::default()),
+            SyntaxKind::LambdaExpr => Expr::Lambda(self.function(node)),
+            SyntaxKind::ListExpr => Expr::List(self.span(node), nodes.iter().map(|item| self.expr(item)).collect()),
+            SyntaxKind::MapExpr => {
+                let entries = nodes
+                    .chunks(2)
+                    .map(|entry| (self.expr(&entry[0]), self.expr(&entry[1])))
+                    .collect();
+                Expr::Map(self.span(node), entries)
+            }
+            SyntaxKind::GroupingExpr => Expr::Grouping(self.span(node), operand(0)),
+            // The literal parts are the tokens between the expressions.
+            SyntaxKind::InterpolationExpr => {
+                let parts = node
+                    .children()
+                    .map(|part| match part {
+                        SyntaxElement::Node(expr) => self.expr(&expr),
+                        SyntaxElement::Token(literal) => Expr::Literal(self.token(&literal)),
+                    })
+                    .collect();
+                Expr::Interpolation(first(), parts)
+            }
+            kind => unreachable!("{kind:?} is not an expression"),
+        }
+    }
+
+    /// Lowers the assignment of `value` to `target` by the operator `op`,
+    /// either `=` or a compound assignment such as `+=`.
+    fn assignment(&self, target: &SyntaxNode, op: &Token, value: Box<Expr>) -> Expr {
+        // A compound assignment `a op= b` keeps `op` as a binary operator,
+        // applied to the current value of `a` and to `b`.
+        let op = match op.token_type {
+            TokenType::PlusEqual => Some(TokenType::Plus),
+            TokenType::MinusEqual => Some(TokenType::Minus),
+            TokenType::StarEqual => Some(TokenType::Star),
+            TokenType::SlashEqual => Some(TokenType::Slash),
+            TokenType::PercentEqual => Some(TokenType::Percent),
+            _ => None,
+        }
+        .map(|binary| {
+            Box::new(Token {
+                token_type: binary,
+                lexeme: op.lexeme.trim_end_matches('=').to_string(),
+                literal: None,
+                span: op.span.clone(),
+            })
+        });
+
+        match self.expr(target) {
+            Expr::Identifier(name, depth) => Expr::Assign(name, op, value, depth),
+            Expr::Get(object, name) => Expr::Set(object, name, op, value),
+            Expr::Index(list, bracket, index) => Expr::SetIndex(list, bracket, index, op, value),
+            _ => unreachable!("only variables, properties and items are parsed as assignment targets"),
+        }
+    }
+
+    /// The name a declaration declares, which is its first identifier.
+    fn name(&self, node: &SyntaxNode) -> Token {
+        let name = node.child_tokens().find(|t| *t.kind() == TokenType::Identifier);
+        self.token(&name.expect("a declaration has a name"))
+    }
+
+    /// The first token directly inside `node`, such as the keyword starting
+    /// a statement or the operator of an operation.
+    fn first_token(&self, node: &SyntaxNode) -> Token {
+        self.token(&node.child_tokens().next().expect("the node has a token"))
+    }
+
+    /// The last token directly inside `node`, such as the `)` of a call.
+    fn last_token(&self, node: &SyntaxNode) -> Token {
+        self.token(&node.child_tokens().last().expect("the node has a token"))
+    }
+
+    /// The tokens directly inside `node`.
+    fn tokens_in(&self, node: &SyntaxNode) -> Vec<Token> {
+        node.child_tokens().map(|token| self.token(&token)).collect()
+    }
+
+    /// The token the parser read where `token` is in the source.
+    fn token(&self, token: &SyntaxToken) -> Token {
+        let start = token.range().start;
+        let i = self
+            .tokens
+            .binary_search_by_key(&start, |t| t.span.start)
+            .expect("every token of the tree was read by the parser");
+        self.tokens[i].clone()
+    }
+
+    /// The span from the first token of `node` to its last.
+    fn span(&self, node: &SyntaxNode) -> Span {
+        let tokens = node.tokens();
+        let first = self.token(&tokens[0]);
+        let last = self.token(&tokens[tokens.len() - 1]);
+        first.span.to(&last.span)
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use super::values::{float_to_int, LoxType, LoxValue};
+
+/// The hashable form of a value used as a map key.
+///
+/// Keys compare the way [`LoxValue`]'s `PartialEq` does: a float with an
+/// integral value is the same key as the equal int, `-0.0` is the same key
+/// as `0.0`, and NaN, which is never equal to itself, cannot be a key.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Key {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Float(u64),
+    String(String),
+}
+
+/// Why a value could not be used as a map key.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum KeyError {
+    Unhashable(LoxType),
+    NaN,
+}
+
+impl Display for KeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unhashable(t) => write!(
+                f,
+                "Map keys must be nil, bool, number or string, found {t}"
+            ),
+            Self::NaN => write!(f, "NaN cannot be used as a map key"),
+        }
+    }
+}
+
+impl TryFrom<&LoxValue> for Key {
+    type Error = KeyError;
+
+    fn try_from(value: &LoxValue) -> Result<Self, Self::Error> {
+        match value {
+            LoxValue::Nil => Ok(Self::Nil),
+            LoxValue::Bool(b) => Ok(Self::Bool(*b)),
+            LoxValue::Int(i) => Ok(Self::Int(*i)),
+            LoxValue::Float(f) if f.is_nan() => Err(KeyError::NaN),
+            // Covers `-0.0`, which is stored under the same key as `0.0`.
+            LoxValue::Float(f) => match float_to_int(*f) {
+                Some(i) => Ok(Self::Int(i)),
+                None => Ok(Self::Float(f.to_bits())),
+            },
+            LoxValue::String(s) => Ok(Self::String(s.clone())),
+            value => Err(KeyError::Unhashable(value.lox_type())),
+        }
+    }
+}
+
+/// A map from values to values which iterates in insertion order, so that
+/// printing or looping over a map is reproducible.
+#[derive(Debug, Clone, Default)]
+pub struct LoxMap {
+    entries: Vec<(LoxValue, LoxValue)>,
+    /// The position in `entries` of each key.
+    index: HashMap<Key, usize>,
+}
+
+impl LoxMap {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub(crate) fn get(&self, key: &LoxValue) -> Result<Option<&LoxValue>, KeyError> {
+        let key = Key::try_from(key)?;
+        Ok(self.index.get(&key).map(|&i| &self.entries[i].1))
+    }
+
+    pub(crate) fn contains(&self, key: &LoxValue) -> Result<bool, KeyError> {
+        Ok(self.index.contains_key(&Key::try_from(key)?))
+    }
+
+    /// Sets the value of `key`. A key that is already present keeps its
+    /// place in the iteration order.
+    pub(crate) fn insert(&mut self, key: LoxValue, value: LoxValue) -> Result<(), KeyError> {
+        let hashed = Key::try_from(&key)?;
+        match self.index.get(&hashed) {
+            Some(&i) => self.entries[i].1 = value,
+            None => {
+                let key = match key {
+                    LoxValue::Float(f) => LoxValue::Float(if f == 0.0 { 0.0 } else { f }),
+                    key => key,
+                };
+                self.index.insert(hashed, self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes `key`, returning its value if it was present.
+    pub(crate) fn remove(&mut self, key: &LoxValue) -> Result<Option<LoxValue>, KeyError> {
+        let Some(i) = self.index.remove(&Key::try_from(key)?) else {
+            return Ok(None);
+        };
+        let (_, value) = self.entries.remove(i);
+        for position in self.index.values_mut() {
+            if *position > i {
+                *position -= 1;
+            }
+        }
+        Ok(Some(value))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&LoxValue, &LoxValue)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &LoxValue> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &LoxValue> {
+        self.entries.iter().map(|(_, value)| value)
+    }
+}
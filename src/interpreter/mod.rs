@@ -1,218 +1,459 @@
-use std::fs;
-use std::io;
-
-use dialoguer::theme::ColorfulTheme;
-use dialoguer::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 
 use crate::prelude::*;
 
+mod callable;
+mod class;
+mod diagnostic;
 mod environment;
 mod error;
 mod expression;
+mod format;
+mod lower;
+mod map;
+mod native;
 mod parser;
+mod resolver;
+mod span;
+mod suggestion;
+mod syntax;
 mod token;
 mod values;
 
-use expression::{Decl, /*AstPrinter,*/ Expr, Stmt, Visitor};
-use parser::Parser;
-use token::{Token, TokenType};
-
+use expression::{Decl, /*AstPrinter,*/ Depth, Expr, Stmt, Visitor};
+use resolver::Resolver;
+use token::Token;
+
+pub use callable::Arity;
+pub use diagnostic::{Diagnostic, Label};
+pub use error::{Error, Frame};
+pub use format::format;
+pub(crate) use native::NativeFunction;
+pub use native::{NativeError, NativeResult};
+pub use span::Span;
+pub use syntax::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken, SyntaxTree, Trivia};
+pub use token::TokenType;
+pub use values::{Dialect, LoxType, LoxValue};
+
+use self::callable::LoxFunction;
+use self::class::{LoxClass, LoxInstance};
 use self::environment::Environment;
-
-pub struct LoxInterpreter {
-    mode: InterpreterMode,
-    environment: Environment,
+use self::map::LoxMap;
+
+/// How evaluation of a declaration or statement completed.
+///
+/// Anything other than `Normal` unwinds through enclosing statements until it
+/// reaches the construct that handles it.
+enum Completion {
+    Normal(LoxValue),
+    Break,
+    Continue,
+    Return(LoxValue),
 }
 
-#[derive(PartialEq)]
-enum InterpreterMode {
-    File,
-    Repl,
+impl Completion {
+    fn value(self) -> LoxValue {
+        match self {
+            Self::Normal(value) | Self::Return(value) => value,
+            _ => LoxValue::Nil,
+        }
+    }
 }
 
-impl Default for InterpreterMode {
-    fn default() -> Self {
-        Self::Repl
-    }
+/// The most frames that may be running at once, past which a call fails with
+/// a stack overflow instead of overflowing the native stack.
+///
+/// A debug build takes about 60 KiB of native stack a call, so reaching this
+/// limit takes a stack of at least 64 MiB.
+const MAX_FRAMES: usize = 1000;
+
+pub(crate) struct LoxInterpreter {
+    args: Vec<String>,
+    dialect: Dialect,
+    globals: Environment,
+    environment: Environment,
+    /// The constructs being run, each with where it was entered from.
+    frames: Vec<(String, Span)>,
 }
 
 impl LoxInterpreter {
     pub fn new() -> Self {
+        Self::with_args(Vec::default())
+    }
+
+    /// Creates an interpreter for a script invoked with `args`.
+    pub fn with_args(args: Vec<String>) -> Self {
+        let globals = Environment::default();
+        for native in native::core(&args) {
+            globals.define(
+                native.name().to_string(),
+                LoxValue::NativeFunction(Rc::new(native)),
+            );
+        }
         Self {
-            mode: InterpreterMode::Repl,
-            environment: Environment::default(),
+            args,
+            dialect: Dialect::default(),
+            environment: globals.clone(),
+            globals,
+            frames: Vec::default(),
         }
     }
 
-    pub fn launch(&mut self) -> io::Result<()> {
-        println!("Welcome to Lox interpreter!");
-        let run_modes = &["Run File", "Run REPL"];
-
-        let run_mode = Select::with_theme(&ColorfulTheme::default())
-            .with_prompt("Choose run mode")
-            .default(0)
-            .items(&run_modes[..])
-            .interact()
-            .unwrap();
-
-        match run_modes[run_mode] {
-            "Run File" => self.run_file(),
-            "Run REPL" => self.run_repl(),
-            x => unreachable!("Unknown option: {x}"),
-        }
+    /// Arguments passed to the script on the command line.
+    pub fn args(&self) -> &[String] {
+        &self.args
     }
 
-    fn run_file(&mut self) -> io::Result<()> {
-        self.mode = InterpreterMode::File;
+    pub fn set_dialect(&mut self, dialect: Dialect) {
+        self.dialect = dialect;
+    }
 
-        let paths = fs::read_dir("./examples")?
-            .map(|p| p.unwrap().path().to_string_lossy().to_string())
-            .collect::<Vec<String>>();
+    pub fn define_global(&mut self, name: String, value: LoxValue) {
+        self.globals.define(name, value);
+    }
 
-        let path = FuzzySelect::with_theme(&ColorfulTheme::default())
-            .with_prompt("Choose a Lox file to interpret")
-            .default(0)
-            .items(&paths[..])
-            .interact()
-            .unwrap();
+    pub fn define_native(&mut self, native: NativeFunction) {
+        self.define_global(
+            native.name().to_string(),
+            LoxValue::NativeFunction(Rc::new(native)),
+        );
+    }
 
-        let lox_source: String =
-            String::from_utf8_lossy(&fs::read(paths[path].as_str())?).to_string();
+    pub fn global(&self, name: &str) -> Option<LoxValue> {
+        self.globals.get(name)
+    }
 
-        self.run(lox_source)
+    /// Tokenizes, parses and evaluates `lox_source`, read from `file`,
+    /// returning the value of the last declaration.
+    ///
+    /// Nothing is evaluated if tokenizing or parsing fails; every error found
+    /// is returned instead. Evaluation stops at the first runtime error.
+    pub fn run(&mut self, lox_source: String, file: &str) -> Result<LoxValue, Vec<Error>> {
+        // let printer = AstPrinter;
+        let decls = SyntaxTree::parse(&lox_source, file).into_decls()?;
+        Resolver::resolve(&decls)?;
+
+        self.in_frame("<script>".to_string(), Span::default(), |this| {
+            let mut output = LoxValue::Nil;
+            for decl in decls.iter() {
+                // println!("Expression: {:}", decl.accept(&printer).unwrap());
+                output = decl.accept(this).map(Completion::value)?;
+            }
+            Ok(output)
+        })
+        .map_err(|e| vec![e])
     }
 
-    fn run_repl(&mut self) -> io::Result<()> {
-        println!("Lox REPL (enter `exit` to quit)");
+    /// Evaluates the object of the property access `object.name`, which must
+    /// be an instance.
+    fn instance(&mut self, object: &Expr, name: &Token) -> Result<Rc<RefCell<LoxInstance>>, Error> {
+        match object.accept(self)? {
+            LoxValue::Instance(instance) => Ok(instance),
+            value => Err(error::Error::WrongType(
+                name.span.clone(),
+                object.to_owned(),
+                value.lox_type(),
+                vec![values::LoxType::Instance],
+            )),
+        }
+    }
 
-        loop {
-            let input: String = Input::with_theme(&ColorfulTheme::default())
-                .with_prompt("> ")
-                .interact_text()
-                .unwrap();
-
-            match input.trim().to_lowercase().as_str() {
-                "exit" => break,
-                _ => match self.run(input) {
-                    Err(e) => eprintln!("{e}"),
-                    _ => {}
-                },
-            };
+    /// Evaluates the list or map being indexed by the bracket `bracket`.
+    fn collection(&mut self, collection: &Expr, bracket: &Token) -> Result<LoxValue, Error> {
+        match collection.accept(self)? {
+            value @ (LoxValue::List(_) | LoxValue::Map(_)) => Ok(value),
+            value => Err(error::Error::WrongType(
+                bracket.span.clone(),
+                collection.to_owned(),
+                value.lox_type(),
+                vec![values::LoxType::List, values::LoxType::Map],
+            )),
         }
+    }
+
+    /// Evaluates the index of the indexing expression `expr` into a position
+    /// in a list of `len` items.
+    fn index(&mut self, expr: &Expr, bracket: &Token, index: &Expr, len: usize) -> Result<usize, Error> {
+        let value = index.accept(self)?;
+        values::list_index(&value, len).map_err(|e| match e {
+            values::IndexError::WrongType(actual) => error::Error::WrongType(
+                bracket.span.clone(),
+                index.to_owned(),
+                actual,
+                vec![values::LoxType::Int],
+            ),
+            e => error::Error::RuntimeError(bracket.span.clone(), expr.to_owned(), e.to_string()),
+        })
+    }
+
+    /// The property `name` of `instance`, read by `expr`.
+    fn property(expr: &Expr, instance: &Rc<RefCell<LoxInstance>>, name: &Token) -> Result<LoxValue, Error> {
+        LoxInstance::get(instance, &name.lexeme).ok_or_else(|| {
+            error::Error::RuntimeError(
+                name.span.clone(),
+                expr.to_owned(),
+                format!("Undefined property: {:}", name.lexeme),
+            )
+        })
+    }
+
+    /// The value of `key` in `map`, read by `expr` with the bracket
+    /// `bracket`.
+    fn entry(expr: &Expr, bracket: &Token, map: &RefCell<LoxMap>, key: &LoxValue) -> Result<LoxValue, Error> {
+        let value = map
+            .borrow()
+            .get(key)
+            .map_err(|e| error::Error::RuntimeError(bracket.span.clone(), expr.to_owned(), e.to_string()))?
+            .cloned();
+        value.ok_or_else(|| {
+            error::Error::RuntimeError(bracket.span.clone(), expr.to_owned(), format!("Key {key} is not in the map"))
+        })
+    }
+
+    /// Evaluates `value`, the value assigned by `expr`. A compound
+    /// assignment applies its operator `op` to the current value of the
+    /// target, read by `current` before `value` is evaluated, and to `value`.
+    fn assigned<F>(&mut self, expr: &Expr, op: Option<&Token>, value: &Expr, current: F) -> Result<LoxValue, Error>
+    where
+        F: FnOnce(&mut Self) -> Result<LoxValue, Error>,
+    {
+        let Some(op) = op else {
+            return value.accept(self);
+        };
+        let current = current(self)?;
+        let value = value.accept(self)?;
+        expression::binary_operation(expr, current, op, value, self.dialect)
+    }
 
-        Ok(())
+    /// The error for `name` not being defined, suggesting a visible name or
+    /// a keyword it may be a misspelling of.
+    fn undefined(&self, name: &Token) -> Error {
+        let names = self.environment.names();
+        let keywords = token::KEYWORDS.iter().map(|(keyword, _)| *keyword);
+        let suggestion = suggestion::did_you_mean(&name.lexeme, names.iter().map(String::as_str).chain(keywords));
+        error::Error::UndefinedVariable(name.span.clone(), name.lexeme.clone(), suggestion.map(str::to_string))
     }
 
-    fn run(&mut self, lox_source: String) -> io::Result<()> {
-        let tokens = Token::tokenize(lox_source);
-
-        let errs = tokens
-            .clone()
-            .iter()
-            .filter(|r| r.is_err())
-            .map(|r| r.clone().err().unwrap())
-            .map(|e| format!("{e}"))
-            .fold(String::default(), |l, r| l + r.as_str());
-
-        if errs.len() > 0 {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Errors occurred while tokenizing source:\n{errs}"),
-            ));
+    fn look_up(&self, name: &Token, depth: &Depth) -> Option<LoxValue> {
+        match depth.get() {
+            Some(depth) => self.environment.get_at(depth, &name.lexeme),
+            None => self.globals.get(&name.lexeme),
         }
+    }
 
-        let tokens = tokens
-            .iter()
-            .map(|r| r.clone().unwrap())
-            .filter(|t| t.token_type != TokenType::Whitespace)
-            .collect::<Vec<Token>>();
+    /// Runs `f` with `environment` as the innermost scope, restoring the
+    /// current scope afterwards even if `f` fails.
+    fn scoped<T, F>(&mut self, environment: Environment, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut Self) -> Result<T, Error>,
+    {
+        let previous = std::mem::replace(&mut self.environment, environment);
+        let result = f(self);
+        self.environment = previous;
+        result
+    }
 
-        // let printer = AstPrinter;
-        let mut parser = Parser::new(tokens);
-        let exprs = parser.parse();
-
-        for expr in exprs.iter() {
-            match expr {
-                Ok(expr) => {
-                    // println!("Expression: {:}", expr.accept(&printer).unwrap());
-                    match expr.accept(self) {
-                        Ok(output) => match self.mode {
-                            InterpreterMode::Repl => println!("{output}"),
-                            _ => {}
-                        },
-                        Err(e) => eprintln!("{e}"),
-                    };
+    /// Runs `f` in a frame named `name`, entered from `entered_at`, attaching
+    /// a backtrace to any runtime error raised within it.
+    fn in_frame<T, F>(&mut self, name: String, entered_at: Span, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut Self) -> Result<T, Error>,
+    {
+        self.frames.push((name, entered_at));
+        let result = f(self).map_err(|e| match e.is_runtime() && e.backtrace().is_none() {
+            true => {
+                // Each frame is at the place the next one in was entered.
+                let mut span = e.span().cloned().unwrap_or_default();
+                let mut backtrace = vec![];
+                for (name, entered_at) in self.frames.iter().rev() {
+                    backtrace.push(Frame {
+                        name: name.clone(),
+                        span: std::mem::replace(&mut span, entered_at.clone()),
+                    });
+                }
+                Error::WithBacktrace(Box::new(e), backtrace)
+            }
+            false => e,
+        });
+        self.frames.pop();
+        result
+    }
+
+    fn execute_block(&mut self, decls: &[Decl], environment: Environment) -> Result<Completion, Error> {
+        self.scoped(environment, |this| {
+            for decl in decls.iter() {
+                match decl.accept(this)? {
+                    Completion::Normal(_) => {}
+                    completion => return Ok(completion),
                 }
-                Err(e) => eprintln!("{e}"),
-            };
+            }
+            Ok(Completion::Normal(LoxValue::Nil))
+        })
+    }
+
+    /// Evaluates `body` for as long as `cond` holds, evaluating `incr` after
+    /// every iteration that is not cut short by `break`.
+    fn execute_loop(
+        &mut self,
+        tok: &Token,
+        cond: Option<&Expr>,
+        incr: Option<&Expr>,
+        body: &Stmt,
+    ) -> Result<Completion, Error> {
+        loop {
+            if let Some(cond) = cond {
+                if !self.condition(tok, cond)? {
+                    break;
+                }
+            }
+            match body.accept(self)? {
+                Completion::Break => break,
+                Completion::Normal(_) | Completion::Continue => {}
+                completion @ Completion::Return(_) => return Ok(completion),
+            }
+            if let Some(incr) = incr {
+                incr.accept(self)?;
+            }
         }
+        Ok(Completion::Normal(LoxValue::Nil))
+    }
+
+    /// Evaluates the condition of the `if`, `while` or `for` statement
+    /// introduced by `tok`.
+    fn condition(&mut self, tok: &Token, cond: &Expr) -> Result<bool, Error> {
+        let value = cond.accept(self)?;
+        self.dialect.truthiness(&value).ok_or_else(|| {
+            error::Error::RuntimeError(
+                tok.span.clone(),
+                cond.to_owned(),
+                "Expected condition to resolve to boolean value".to_string(),
+            )
+        })
+    }
 
-        Ok(())
+    /// Evaluates an operand of the logical operator `op`, which must be
+    /// usable as a boolean, returning it along with its truthiness.
+    fn operand(&mut self, op: &Token, operand: &Expr) -> Result<(LoxValue, bool), Error> {
+        let value = operand.accept(self)?;
+        match self.dialect.truthiness(&value) {
+            Some(truthy) => Ok((value, truthy)),
+            None => Err(error::Error::WrongType(
+                op.span.clone(),
+                operand.to_owned(),
+                value.lox_type(),
+                vec![LoxType::Bool],
+            )),
+        }
     }
 }
 
-impl Visitor<values::LoxValue, Decl> for LoxInterpreter {
-    fn visit(&mut self, decl: &Decl) -> Result<values::LoxValue, error::Error> {
+impl Visitor<Completion, Decl> for LoxInterpreter {
+    fn visit(&mut self, decl: &Decl) -> Result<Completion, error::Error> {
         use values::LoxValue;
         match decl {
-            Decl::Declaration(tok, expr) => {
+            Decl::Declaration(_, tok, expr) => {
                 let value = match expr {
                     Some(expr) => expr.accept(self)?,
                     None => LoxValue::Nil,
                 };
                 self.environment
                     .define(tok.lexeme.to_owned(), value.clone());
-                Ok(value)
+                Ok(Completion::Normal(value))
+            }
+            Decl::Function(f) => {
+                let function = LoxFunction::new(f.clone(), self.environment.clone());
+                let value = LoxValue::Function(Rc::new(function));
+                if let Some(name) = &f.name {
+                    self.environment.define(name.lexeme.to_owned(), value.clone());
+                }
+                Ok(Completion::Normal(value))
+            }
+            Decl::Class(_, name, superclass_expr, methods) => {
+                let superclass = match superclass_expr {
+                    Some(expr) => match expr.accept(self)? {
+                        LoxValue::Class(class) => Some(class),
+                        value => {
+                            return Err(error::Error::WrongType(
+                                name.span.clone(),
+                                expr.to_owned(),
+                                value.lox_type(),
+                                vec![values::LoxType::Class],
+                            ))
+                        }
+                    },
+                    None => None,
+                };
+
+                let closure = match &superclass {
+                    Some(superclass) => {
+                        let closure = Environment::enclose(&self.environment);
+                        closure.define("super".to_string(), LoxValue::Class(superclass.clone()));
+                        closure
+                    }
+                    None => self.environment.clone(),
+                };
+                let methods = methods
+                    .iter()
+                    .filter_map(|m| {
+                        let name = m.name.as_ref()?.lexeme.clone();
+                        Some((name, Rc::new(LoxFunction::method(m.clone(), closure.clone()))))
+                    })
+                    .collect::<HashMap<_, _>>();
+
+                let class = LoxClass::new(name.lexeme.clone(), superclass, methods);
+                let value = LoxValue::Class(Rc::new(class));
+                self.environment.define(name.lexeme.to_owned(), value.clone());
+                Ok(Completion::Normal(value))
             }
             Decl::Statement(expr) => expr.accept(self),
         }
     }
 }
 
-impl Visitor<values::LoxValue, Stmt> for LoxInterpreter {
-    fn visit(&mut self, stmt: &Stmt) -> Result<values::LoxValue, error::Error> {
-        use values::{LoxType, LoxValue};
+impl Visitor<Completion, Stmt> for LoxInterpreter {
+    fn visit(&mut self, stmt: &Stmt) -> Result<Completion, error::Error> {
+        use values::LoxValue;
         match stmt {
-            Stmt::Expression(e) => e.accept(self),
+            Stmt::Expression(_, e) => e.accept(self).map(Completion::Normal),
             Stmt::If(tok, ie, s, ee) => {
-                let cond = ie.accept(self)?;
-                if cond.lox_type() != LoxType::Bool {
-                    return Err(error::Error::RuntimeError(
-                        tok.line,
-                        ie.to_owned(),
-                        "Expected condition to resolve to boolean value".to_string(),
-                    ));
-                }
-                if cond == LoxValue::Bool(true) {
+                if self.condition(tok, ie)? {
                     return s.accept(self);
                 }
 
                 match ee {
-                    None => Ok(LoxValue::Nil),
+                    None => Ok(Completion::Normal(LoxValue::Nil)),
                     Some(ee) => ee.accept(self),
                 }
             }
-            Stmt::Print(e) => {
+            Stmt::Print(_, e) => {
                 let e = e.accept(self)?;
                 println!("{e}");
-                Ok(LoxValue::Nil)
+                Ok(Completion::Normal(LoxValue::Nil))
             }
-            Stmt::Block(decls) => {
-                self.environment.create_enclosing();
-                for d in decls.iter() {
-                    let output = match d.accept(self) {
-                        Ok(output) => output,
-                        err => {
-                            self.environment.drop_enclosing();
-                            return err;
-                        }
-                    };
-                    if self.mode == InterpreterMode::Repl {
-                        println!("{output}");
+            Stmt::Block(span, decls) => {
+                let environment = Environment::enclose(&self.environment);
+                self.in_frame("<block>".to_string(), span.clone(), |this| this.execute_block(decls, environment))
+            }
+            Stmt::While(tok, cond, body) => self.execute_loop(tok, Some(cond), None, body),
+            Stmt::For(tok, init, cond, incr, body) => {
+                let environment = Environment::enclose(&self.environment);
+                self.scoped(environment, |this| {
+                    if let Some(init) = init {
+                        init.accept(this)?;
                     }
-                }
-                self.environment.drop_enclosing();
-                Ok(LoxValue::Nil)
+                    this.execute_loop(tok, cond.as_ref(), incr.as_ref(), body)
+                })
+            }
+            Stmt::Break(..) => Ok(Completion::Break),
+            Stmt::Continue(..) => Ok(Completion::Continue),
+            Stmt::Return(_, _, value) => {
+                let value = match value {
+                    Some(value) => value.accept(self)?,
+                    None => LoxValue::Nil,
+                };
+                Ok(Completion::Return(value))
             }
         }
     }
@@ -223,83 +464,221 @@ impl Visitor<values::LoxValue, Expr> for LoxInterpreter {
         use values::{LoxType, LoxValue};
         match expr {
             Expr::Literal(tok) => Ok(LoxValue::from(tok.clone())),
-            Expr::Identifier(tok) => match self.environment.get(&tok.lexeme) {
-                Some(v) => Ok(v.clone()),
-                _ => Err(error::Error::RuntimeError(
-                    tok.line,
-                    expr.to_owned(),
-                    format!("Undefined variable: {:}", tok.lexeme),
-                )),
-            },
-            Expr::Assign(ident, sub_expr) => {
-                let value = sub_expr.accept(self)?;
-                let _ = self
-                    .environment
-                    .update(ident.lexeme.to_owned(), value)
-                    .ok_or(error::Error::UndefinedVariable(
-                        ident.line,
-                        ident.lexeme.clone(),
-                    ))?;
-                Ok(LoxValue::Nil)
+            Expr::Interpolation(_, parts) => {
+                let mut string = String::default();
+                for part in parts.iter() {
+                    string.push_str(&part.accept(self)?.to_string());
+                }
+                Ok(LoxValue::String(string))
             }
-            Expr::Grouping(sub_expr) => sub_expr.accept(self),
+            Expr::Identifier(tok, depth) => self.look_up(tok, depth).ok_or_else(|| self.undefined(tok)),
+            Expr::Assign(ident, op, sub_expr, depth) => {
+                let value = self.assigned(expr, op.as_deref(), sub_expr, |this| {
+                    this.look_up(ident, depth).ok_or_else(|| this.undefined(ident))
+                })?;
+                let previous = match depth.get() {
+                    Some(depth) => self.environment.update_at(depth, &ident.lexeme, value.clone()),
+                    None => self.globals.update(&ident.lexeme, value.clone()),
+                };
+                previous.ok_or_else(|| self.undefined(ident))?;
+                Ok(value)
+            }
+            Expr::Grouping(_, sub_expr) => sub_expr.accept(self),
             Expr::Unary(op, sub_expr) => match op.token_type.clone() {
                 TokenType::Minus => {
                     let output = sub_expr.accept(self)?;
                     match output {
-                        LoxValue::Number(f) => Ok(LoxValue::Number(-1.0_f64 * f)),
+                        LoxValue::Int(i) => i.checked_neg().map(LoxValue::Int).ok_or_else(|| {
+                            error::Error::RuntimeError(op.span.clone(), expr.to_owned(), "Integer overflow".to_string())
+                        }),
+                        LoxValue::Float(f) => Ok(LoxValue::Float(-f)),
                         _ => Err(error::Error::WrongType(
-                            op.line,
+                            op.span.clone(),
                             expr.to_owned(),
                             output.lox_type(),
-                            LoxType::Number,
+                            vec![LoxType::Int, LoxType::Float],
                         )),
                     }
                 }
-                TokenType::Bang => {
+                TokenType::Tilde => {
                     let output = sub_expr.accept(self)?;
                     match output {
-                        LoxValue::Bool(b) => Ok(LoxValue::Bool(!b)),
+                        LoxValue::Int(i) => Ok(LoxValue::Int(!i)),
                         _ => Err(error::Error::WrongType(
-                            op.line,
+                            op.span.clone(),
                             expr.to_owned(),
                             output.lox_type(),
-                            LoxType::Bool,
+                            vec![LoxType::Int],
+                        )),
+                    }
+                }
+                TokenType::Bang => {
+                    let output = sub_expr.accept(self)?;
+                    match self.dialect.truthiness(&output) {
+                        Some(truthy) => Ok(LoxValue::Bool(!truthy)),
+                        None => Err(error::Error::WrongType(
+                            op.span.clone(),
+                            expr.to_owned(),
+                            output.lox_type(),
+                            vec![LoxType::Bool],
                         )),
                     }
                 }
                 _ => unreachable!("Unary operator: {:?}", op.token_type),
             },
+            Expr::Ternary(condition, question, then, otherwise) => match self.condition(question, condition)? {
+                true => then.accept(self),
+                false => otherwise.accept(self),
+            },
+            // The right operand is only evaluated when the left one does not
+            // decide the result, and whichever operand decides it is returned.
             Expr::Logical(left, op, right) => {
-                let left = left.accept(self)?;
-                let right = right.accept(self)?;
-                if left.lox_type() == LoxType::Bool && left.is_same_type_as(&right) {
-                    return error::Error::InoperableTypes(
-                        op.to_owned(),
+                let (left, truthy) = self.operand(op, left)?;
+                let decided = match op.token_type {
+                    TokenType::Or => truthy,
+                    TokenType::And => !truthy,
+                    _ => unreachable!("Logical operator: {:?}", op.token_type),
+                };
+                if decided {
+                    return Ok(left);
+                }
+                self.operand(op, right).map(|(right, _)| right)
+            }
+            Expr::Binary(left, op, right) => {
+                let dialect = self.dialect;
+                expression::visit_binary_expression(expr, left, op, right, dialect, self)
+            }
+            Expr::Call(callee, paren, args) => {
+                let callee = callee.accept(self)?;
+                let args = args
+                    .iter()
+                    .map(|arg| arg.accept(self))
+                    .collect::<Result<Vec<LoxValue>, error::Error>>()?;
+
+                let Some(function) = callee.as_callable() else {
+                    return Err(error::Error::WrongType(
+                        paren.span.clone(),
+                        expr.to_owned(),
+                        callee.lox_type(),
+                        vec![LoxType::Function],
+                    ));
+                };
+                let arity = function.arity();
+                if !arity.accepts(args.len()) {
+                    return Err(error::Error::RuntimeError(
+                        paren.span.clone(),
                         expr.to_owned(),
-                        vec![LoxType::Bool],
-                        left.lox_type(),
-                        right.lox_type(),
-                    )
-                    .to_result();
+                        format!("Expected {arity} arguments but got {}", args.len()),
+                    ));
                 }
-                match op.token_type {
-                    TokenType::Or => Ok(LoxValue::Bool(
-                        left.into_bool().unwrap() || right.into_bool().unwrap(),
-                    )),
-                    TokenType::And => Ok(LoxValue::Bool(
-                        left.into_bool().unwrap() && right.into_bool().unwrap(),
+                if self.frames.len() >= MAX_FRAMES {
+                    return Err(error::Error::RuntimeError(
+                        paren.span.clone(),
+                        expr.to_owned(),
+                        "Stack overflow".to_string(),
+                    ));
+                }
+                self.in_frame(function.frame_name(), expr.span(), |this| function.call(this, expr, args))
+            }
+            Expr::Lambda(f) => {
+                let function = LoxFunction::new(f.clone(), self.environment.clone());
+                Ok(LoxValue::Function(Rc::new(function)))
+            }
+            Expr::Get(object, name) => {
+                let instance = self.instance(object, name)?;
+                Self::property(expr, &instance, name)
+            }
+            Expr::Set(object, name, op, value) => {
+                let instance = self.instance(object, name)?;
+                let value = self.assigned(expr, op.as_deref(), value, |_| Self::property(expr, &instance, name))?;
+                instance
+                    .borrow_mut()
+                    .set(name.lexeme.clone(), value.clone());
+                Ok(value)
+            }
+            Expr::List(_, items) => {
+                let items = items
+                    .iter()
+                    .map(|item| item.accept(self))
+                    .collect::<Result<Vec<LoxValue>, error::Error>>()?;
+                Ok(LoxValue::List(Rc::new(RefCell::new(items))))
+            }
+            Expr::Map(_, entries) => {
+                let mut map = LoxMap::default();
+                for (key_expr, value) in entries.iter() {
+                    let key = key_expr.accept(self)?;
+                    let value = value.accept(self)?;
+                    map.insert(key, value)
+                        .map_err(|e| error::Error::RuntimeError(key_expr.span(), key_expr.to_owned(), e.to_string()))?;
+                }
+                Ok(LoxValue::Map(Rc::new(RefCell::new(map))))
+            }
+            Expr::Index(collection, bracket, index) => match self.collection(collection, bracket)? {
+                LoxValue::Map(map) => {
+                    let key = index.accept(self)?;
+                    Self::entry(expr, bracket, &map, &key)
+                }
+                LoxValue::List(list) => {
+                    let len = list.borrow().len();
+                    let index = self.index(expr, bracket, index, len)?;
+                    let item = list.borrow()[index].clone();
+                    Ok(item)
+                }
+                _ => unreachable!("Indexed a value that is not a collection"),
+            },
+            Expr::SetIndex(collection, bracket, index, op, value) => match self.collection(collection, bracket)? {
+                LoxValue::Map(map) => {
+                    let key = index.accept(self)?;
+                    let value = self.assigned(expr, op.as_deref(), value, |_| Self::entry(expr, bracket, &map, &key))?;
+                    map.borrow_mut()
+                        .insert(key, value.clone())
+                        .map_err(|e| error::Error::RuntimeError(bracket.span.clone(), expr.to_owned(), e.to_string()))?;
+                    Ok(value)
+                }
+                LoxValue::List(list) => {
+                    let len = list.borrow().len();
+                    let index = self.index(expr, bracket, index, len)?;
+                    let value = self.assigned(expr, op.as_deref(), value, |_| Ok(list.borrow()[index].clone()))?;
+                    // Evaluating `value` may have shrunk the list.
+                    match list.borrow_mut().get_mut(index) {
+                        Some(slot) => *slot = value.clone(),
+                        None => {
+                            return Err(error::Error::RuntimeError(
+                                bracket.span.clone(),
+                                expr.to_owned(),
+                                values::IndexError::OutOfRange(index as i64, len).to_string(),
+                            ))
+                        }
+                    }
+                    Ok(value)
+                }
+                _ => unreachable!("Indexed a value that is not a collection"),
+            },
+            Expr::This(keyword, depth) => self.look_up(keyword, depth).ok_or_else(|| {
+                error::Error::UndefinedVariable(keyword.span.clone(), keyword.lexeme.clone(), None)
+            }),
+            Expr::Super(keyword, method, depth) => {
+                // `this` is bound in the scope just inside the one binding `super`.
+                let depth = depth.get().unwrap_or_default();
+                let superclass = self.environment.get_at(depth, &keyword.lexeme);
+                let instance = self.environment.get_at(depth.saturating_sub(1), "this");
+                let (Some(LoxValue::Class(superclass)), Some(instance)) = (superclass, instance) else {
+                    return Err(error::Error::UndefinedVariable(
+                        keyword.span.clone(),
+                        keyword.lexeme.clone(),
+                        None,
+                    ));
+                };
+
+                match superclass.find_method(&method.lexeme) {
+                    Some(m) => Ok(LoxValue::Function(Rc::new(m.bind(instance)))),
+                    None => Err(error::Error::RuntimeError(
+                        method.span.clone(),
+                        expr.to_owned(),
+                        format!("Undefined property: {:}", method.lexeme),
                     )),
-                    _ => unreachable!("Logical operator: {:?}", op.token_type),
                 }
             }
-            Expr::Binary(left, op, right) => expression::visit_binary_expression(
-                expr,
-                left.clone(),
-                op.clone(),
-                right.clone(),
-                self,
-            ),
         }
     }
 }
@@ -0,0 +1,252 @@
+use std::cell::RefCell;
+use std::fmt::{Debug, Formatter};
+use std::io::{self, BufRead};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{
+    callable::{Arity, Callable},
+    error::Error,
+    expression::Expr,
+    map::LoxMap,
+    values::{float_to_int, list_index, IndexError, LoxType, LoxValue},
+    LoxInterpreter,
+};
+
+pub type NativeResult = Result<LoxValue, NativeError>;
+
+type NativeFn = dyn Fn(&[LoxValue]) -> NativeResult;
+
+/// Why a native function failed.
+///
+/// Native functions do not know where they were called from, so the
+/// interpreter turns this into an [`Error`] pointing at the call site.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NativeError {
+    /// The argument at the given index was of the given type where one of
+    /// the listed types was expected.
+    WrongType(usize, LoxType, Vec<LoxType>),
+    Failed(String),
+    /// The script asked for the process to exit with the given status.
+    Exit(u8),
+}
+
+impl NativeError {
+    fn into_error(self, call: &Expr) -> Error {
+        let Expr::Call(_, paren, args) = call else {
+            unreachable!("Native function called from {call:?}");
+        };
+        match self {
+            Self::WrongType(index, actual, expected) => Error::WrongType(
+                paren.span.clone(),
+                args.get(index).unwrap_or(call).to_owned(),
+                actual,
+                expected,
+            ),
+            Self::Failed(message) => Error::RuntimeError(paren.span.clone(), call.to_owned(), message),
+            Self::Exit(code) => Error::Exit(code),
+        }
+    }
+}
+
+/// A function implemented in Rust and callable from Lox.
+pub struct NativeFunction {
+    name: String,
+    arity: Arity,
+    function: Box<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new<F>(name: &str, arity: Arity, function: F) -> Self
+    where
+        F: Fn(&[LoxValue]) -> NativeResult + 'static,
+    {
+        Self {
+            name: name.to_string(),
+            arity,
+            function: Box::new(function),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Callable for NativeFunction {
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn frame_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn call(&self, _interpreter: &mut LoxInterpreter, call: &Expr, args: Vec<LoxValue>) -> Result<LoxValue, Error> {
+        (self.function)(&args).map_err(|e| e.into_error(call))
+    }
+}
+
+impl Debug for NativeFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish()
+    }
+}
+
+/// Returns the list argument at `index`.
+fn list_arg(args: &[LoxValue], index: usize) -> Result<&Rc<RefCell<Vec<LoxValue>>>, NativeError> {
+    match &args[index] {
+        LoxValue::List(list) => Ok(list),
+        value => Err(NativeError::WrongType(index, value.lox_type(), vec![LoxType::List])),
+    }
+}
+
+/// Returns the map argument at `index`.
+fn map_arg(args: &[LoxValue], index: usize) -> Result<&Rc<RefCell<LoxMap>>, NativeError> {
+    match &args[index] {
+        LoxValue::Map(map) => Ok(map),
+        value => Err(NativeError::WrongType(index, value.lox_type(), vec![LoxType::Map])),
+    }
+}
+
+/// Converts the argument at `index` into a position in a list of `len` items.
+fn index_arg(args: &[LoxValue], index: usize, len: usize) -> Result<usize, NativeError> {
+    list_index(&args[index], len).map_err(|e| match e {
+        IndexError::WrongType(actual) => NativeError::WrongType(index, actual, vec![LoxType::Int]),
+        e => NativeError::Failed(e.to_string()),
+    })
+}
+
+/// The native functions every interpreter starts out with, for a script
+/// invoked with `args`.
+pub fn core(args: &[String]) -> Vec<NativeFunction> {
+    let script_args = args.to_vec();
+    vec![
+        NativeFunction::new("clock", Arity::Fixed(0), |_| {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_err(|e| NativeError::Failed(e.to_string()))?;
+            Ok(LoxValue::Float(now.as_secs_f64()))
+        }),
+        NativeFunction::new("typeof", Arity::Fixed(1), |args| {
+            Ok(LoxValue::String(args[0].lox_type().to_string()))
+        }),
+        NativeFunction::new("str", Arity::Fixed(1), |args| {
+            Ok(LoxValue::String(args[0].to_string()))
+        }),
+        NativeFunction::new("num", Arity::Fixed(1), |args| match &args[0] {
+            value @ (LoxValue::Int(_) | LoxValue::Float(_)) => Ok(value.clone()),
+            LoxValue::String(s) => {
+                let s = s.trim();
+                s.parse::<i64>()
+                    .map(LoxValue::Int)
+                    .or_else(|_| s.parse::<f64>().map(LoxValue::Float))
+                    .map_err(|_| NativeError::Failed(format!("Cannot convert \"{s}\" to a number")))
+            }
+            value => Err(NativeError::WrongType(
+                0,
+                value.lox_type(),
+                vec![LoxType::Int, LoxType::Float, LoxType::String],
+            )),
+        }),
+        NativeFunction::new("int", Arity::Fixed(1), |args| match &args[0] {
+            LoxValue::Int(i) => Ok(LoxValue::Int(*i)),
+            // Truncates towards zero.
+            LoxValue::Float(f) => float_to_int(f.trunc())
+                .map(LoxValue::Int)
+                .ok_or_else(|| NativeError::Failed(format!("{f:?} is out of range for an int"))),
+            value => Err(NativeError::WrongType(0, value.lox_type(), vec![LoxType::Int, LoxType::Float])),
+        }),
+        NativeFunction::new("float", Arity::Fixed(1), |args| match &args[0] {
+            LoxValue::Int(i) => Ok(LoxValue::Float(*i as f64)),
+            LoxValue::Float(f) => Ok(LoxValue::Float(*f)),
+            value => Err(NativeError::WrongType(0, value.lox_type(), vec![LoxType::Int, LoxType::Float])),
+        }),
+        NativeFunction::new("len", Arity::Fixed(1), |args| match &args[0] {
+            LoxValue::String(s) => Ok(LoxValue::Int(s.chars().count() as i64)),
+            LoxValue::List(list) => Ok(LoxValue::Int(list.borrow().len() as i64)),
+            LoxValue::Map(map) => Ok(LoxValue::Int(map.borrow().len() as i64)),
+            value => Err(NativeError::WrongType(
+                0,
+                value.lox_type(),
+                vec![LoxType::String, LoxType::List, LoxType::Map],
+            )),
+        }),
+        NativeFunction::new("push", Arity::Fixed(2), |args| {
+            list_arg(args, 0)?.borrow_mut().push(args[1].clone());
+            Ok(LoxValue::Nil)
+        }),
+        NativeFunction::new("pop", Arity::Fixed(1), |args| {
+            list_arg(args, 0)?
+                .borrow_mut()
+                .pop()
+                .ok_or_else(|| NativeError::Failed("Cannot pop from an empty list".to_string()))
+        }),
+        NativeFunction::new("insert", Arity::Fixed(3), |args| {
+            let list = list_arg(args, 0)?;
+            // Inserting at the end of the list is allowed.
+            let len = list.borrow().len();
+            let index = index_arg(args, 1, len + 1)?;
+            list.borrow_mut().insert(index, args[2].clone());
+            Ok(LoxValue::Nil)
+        }),
+        NativeFunction::new("remove", Arity::Fixed(2), |args| {
+            if let LoxValue::Map(map) = &args[0] {
+                return map
+                    .borrow_mut()
+                    .remove(&args[1])
+                    .map_err(|e| NativeError::Failed(e.to_string()))?
+                    .ok_or_else(|| NativeError::Failed(format!("Key {} is not in the map", args[1])));
+            }
+            let list = list_arg(args, 0)
+                .map_err(|_| NativeError::WrongType(0, args[0].lox_type(), vec![LoxType::List, LoxType::Map]))?;
+            let len = list.borrow().len();
+            let index = index_arg(args, 1, len)?;
+            let item = list.borrow_mut().remove(index);
+            Ok(item)
+        }),
+        NativeFunction::new("has", Arity::Fixed(2), |args| {
+            map_arg(args, 0)?
+                .borrow()
+                .contains(&args[1])
+                .map(LoxValue::Bool)
+                .map_err(|e| NativeError::Failed(e.to_string()))
+        }),
+        NativeFunction::new("keys", Arity::Fixed(1), |args| {
+            let keys = map_arg(args, 0)?.borrow().keys().cloned().collect();
+            Ok(LoxValue::List(Rc::new(RefCell::new(keys))))
+        }),
+        NativeFunction::new("values", Arity::Fixed(1), |args| {
+            let values = map_arg(args, 0)?.borrow().values().cloned().collect();
+            Ok(LoxValue::List(Rc::new(RefCell::new(values))))
+        }),
+        NativeFunction::new("args", Arity::Fixed(0), move |_| {
+            let args = script_args.iter().cloned().map(LoxValue::String).collect();
+            Ok(LoxValue::List(Rc::new(RefCell::new(args))))
+        }),
+        NativeFunction::new("input", Arity::Fixed(0), |_| {
+            let mut line = String::default();
+            let read = io::stdin()
+                .lock()
+                .read_line(&mut line)
+                .map_err(|e| NativeError::Failed(e.to_string()))?;
+            if read == 0 {
+                return Ok(LoxValue::Nil);
+            }
+            let line = line.strip_suffix('\n').unwrap_or(&line);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            Ok(LoxValue::String(line.to_string()))
+        }),
+        NativeFunction::new("exit", Arity::Fixed(1), |args| match &args[0] {
+            // A process can only report a status from 0 to 255.
+            LoxValue::Int(n) => match u8::try_from(*n) {
+                Ok(code) => Err(NativeError::Exit(code)),
+                Err(_) => Err(NativeError::Failed(format!("Invalid exit status {n}, expected 0 to 255"))),
+            },
+            value => Err(NativeError::WrongType(0, value.lox_type(), vec![LoxType::Int])),
+        }),
+    ]
+}
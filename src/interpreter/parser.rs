@@ -1,297 +1,618 @@
+use std::rc::Rc;
+
 use super::{
     error::Error,
-    expression::{AstPrinter, Decl, Expr, Stmt},
+    span::Span,
+    syntax::{self, GreenToken, SyntaxKind, SyntaxNode, TreeBuilder},
     token::*,
 };
 
+/// Parses tokens into a syntax tree, checking that they follow the grammar.
+///
+/// The AST is derived from the tree afterwards by a [`Lowering`].
+///
+/// [`Lowering`]: super::lower::Lowering
 pub struct Parser {
     cursor: i32,
     tokens: Vec<Token>,
+    /// The syntax tree token of each of `tokens`.
+    green: Vec<Rc<GreenToken>>,
+    builder: TreeBuilder,
+    loop_depth: usize,
+    /// How many blocks and class bodies enclose the cursor.
+    nesting: usize,
+    errors: Vec<Error>,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Self { cursor: 0, tokens }
+    pub fn new(tokens: Vec<Token>, green: Vec<Rc<GreenToken>>) -> Self {
+        Self {
+            cursor: 0,
+            tokens,
+            green,
+            builder: TreeBuilder::default(),
+            loop_depth: 0,
+            nesting: 0,
+            errors: vec![],
+        }
     }
 
-    pub fn parse(&mut self) -> Vec<Result<Decl, Error>> {
-        let mut exprs: Vec<Result<Decl, Error>> = Vec::default();
+    /// Parses every declaration, or returns every syntax error found.
+    pub fn parse(&mut self) -> Result<(), Vec<Error>> {
+        while !self.is_eof() {
+            self.recovering(Self::declaration);
+        }
 
-        loop {
-            if self.is_eof() {
-                break;
-            }
+        match self.errors.is_empty() {
+            true => Ok(()),
+            false => Err(std::mem::take(&mut self.errors)),
+        }
+    }
 
-            let expr = self.declaration();
-            if expr.is_err() {
-                self.synchronize();
-            }
+    /// The syntax tree of everything parsed, along with the tokens it was
+    /// parsed from.
+    pub fn finish(mut self) -> (SyntaxNode, Vec<Token>) {
+        let eof = self.green[self.cursor as usize].clone();
+        self.builder.token(eof);
+        (self.builder.finish(), self.tokens)
+    }
 
-            exprs.push(expr);
-        }
+    /// Parses with `parse`, grouping the tokens it consumed into a `kind`
+    /// node of the syntax tree.
+    fn node(&mut self, kind: SyntaxKind, parse: impl FnOnce(&mut Self) -> Result<(), Error>) -> Result<(), Error> {
+        let checkpoint = self.builder.checkpoint();
+        parse(self)?;
+        self.builder.wrap(checkpoint, kind);
+        Ok(())
+    }
 
-        exprs
+    /// Parses with `parse`, or records the syntax error it found and skips
+    /// to where parsing can resume.
+    fn recovering(&mut self, parse: fn(&mut Self) -> Result<(), Error>) {
+        let checkpoint = self.builder.checkpoint();
+        if let Err(e) = parse(self) {
+            // An error at a token that failed to tokenize has been reported
+            // already, as has one at the end of input right after such a
+            // token, which is what a string or comment left open leaves
+            // behind. Another error at the same place as the last one only
+            // follows on from it.
+            let cascades = self.peek().token_type == TokenType::Invalid
+                || (self.is_eof() && self.cursor > 0 && self.previous().token_type == TokenType::Invalid)
+                || self.errors.last().is_some_and(|last| last.span() == e.span());
+            if !cascades {
+                self.errors.push(e);
+            }
+            self.synchronize();
+            self.builder.wrap(checkpoint, SyntaxKind::Error);
+        }
     }
 
+    /// Skips the rest of the statement a syntax error was found in.
+    ///
+    /// The statement ends after a `;` or after braces opened within it are
+    /// closed, or before a keyword starting another statement or a `}`
+    /// closing the enclosing block or class.
     fn synchronize(&mut self) {
         use TokenType::*;
-        self.advance();
-
-        loop {
-            if self.is_eof() {
-                return;
-            }
-            if self.previous().token_type == SemiColon {
-                return;
+        let mut depth = 0;
+        let mut skipped = false;
+
+        while !self.is_eof() {
+            match self.peek().token_type {
+                LeftBrace => depth += 1,
+                RightBrace if depth > 0 => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.advance();
+                        return;
+                    }
+                }
+                RightBrace if self.nesting > 0 => return,
+                SemiColon if depth == 0 => {
+                    self.advance();
+                    return;
+                }
+                Class | Fun | Fn | Var | For | If | While | Print | Return | Break | Continue
+                    if depth == 0 && skipped =>
+                {
+                    return
+                }
+                _ => {}
             }
-            let Some(_) = self.advance_if(|t| {
-                ![Class, Fun, Fn, Var, For, If, While, Print, Return].contains(&t.token_type)
-            }) else {
-                break;
-            };
+            self.advance();
+            skipped = true;
         }
     }
-    fn declaration(&mut self) -> Result<Decl, Error> {
+    fn declaration(&mut self) -> Result<(), Error> {
         use TokenType::*;
-        let Some(_) = self.advance_if(|t| t.token_type == Var) else {
-            let stmt = self.statement();
+        if [Fun, Fn].contains(&self.peek().token_type) && self.peek_offset(1).token_type == Identifier {
+            return self.node(SyntaxKind::FunctionDecl, |this| {
+                this.advance();
+                this.advance();
+                this.function()
+            });
+        }
+
+        if self.peek().token_type == Class {
+            return self.node(SyntaxKind::ClassDecl, Self::class);
+        }
+
+        if self.peek().token_type != Var {
+            self.statement()?;
             self.advance_if(|t| t.token_type == SemiColon);
-            return stmt.map(Decl::Statement);
-        };
+            return Ok(());
+        }
+        self.node(SyntaxKind::VarDecl, Self::variable)
+    }
 
-        let Some(ident) = self.advance_if(|t| t.token_type == Identifier) else {
+    /// Parses a variable declaration.
+    fn variable(&mut self) -> Result<(), Error> {
+        use TokenType::*;
+        self.advance();
+
+        if self.advance_if(|t| t.token_type == Identifier).is_none() {
             let tok = self.peek();
             return Error::UnexpectedToken(
-                tok.line,
+                tok.span.clone(),
                 format!("Found {:?}, expected {:?}", tok.token_type, Identifier),
             )
             .to_result();
-        };
-        let ident = ident.clone();
+        }
 
-        let Some(_) = self.advance_if(|t| t.token_type == Equal) else {
-            self.advance_if(|t| t.token_type == SemiColon);
-            return Ok(Decl::Declaration(ident, None));
+        if self.advance_if(|t| t.token_type == Equal).is_some() {
+            self.expression()?;
+        }
+        self.advance_if(|t| t.token_type == SemiColon);
+
+        Ok(())
+    }
+
+    /// Parses the declaration of a class.
+    fn class(&mut self) -> Result<(), Error> {
+        use TokenType::*;
+        self.advance();
+        self.expect(Identifier, "class name")?;
+        if self.advance_if(|t| t.token_type == Less).is_some() {
+            self.node(SyntaxKind::Name, |this| this.expect(Identifier, "superclass name").map(|_| ()))?;
+        }
+        self.expect(LeftBrace, "{")?;
+
+        self.nesting += 1;
+        let closed = loop {
+            if self.advance_if(|t| t.token_type == RightBrace).is_some() {
+                break Ok(());
+            }
+            if self.is_eof() {
+                break Err(Error::UnexpectedEof(self.peek().span.clone()));
+            }
+            self.recovering(Self::method);
         };
+        self.nesting -= 1;
+        closed
+    }
 
-        let expr = self.expression()?;
-        self.advance_if(|t| t.token_type == SemiColon);
+    fn method(&mut self) -> Result<(), Error> {
+        self.node(SyntaxKind::FunctionDecl, |this| {
+            this.expect(TokenType::Identifier, "method name")?;
+            this.function()
+        })
+    }
+
+    /// Parses the parameters and body of a function, whose keyword or name
+    /// has already been consumed.
+    fn function(&mut self) -> Result<(), Error> {
+        use TokenType::*;
+        self.node(SyntaxKind::ParamList, |this| {
+            this.expect(LeftParen, "(")?;
+            if this.peek().token_type != RightParen {
+                loop {
+                    this.expect(Identifier, "parameter name")?;
+                    if this.advance_if(|t| t.token_type == Comma).is_none() {
+                        break;
+                    }
+                }
+            }
+            this.expect(RightParen, ")").map(|_| ())
+        })?;
+
+        // Loops do not extend into function bodies, so `break` in a function
+        // declared inside a loop is still misplaced.
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let body = self.node(SyntaxKind::Block, |this| {
+            this.expect(LeftBrace, "{")?;
+            this.block()
+        });
+        self.loop_depth = loop_depth;
+        body
+    }
+
+    /// Parses the declarations of a block whose `{` has already been consumed.
+    ///
+    /// A syntax error in one of the declarations is recorded, and parsing
+    /// resumes with the next one.
+    fn block(&mut self) -> Result<(), Error> {
+        self.nesting += 1;
+        let closed = loop {
+            if self.advance_if(|t| t.token_type == TokenType::RightBrace).is_some() {
+                break Ok(());
+            }
+            if self.is_eof() {
+                break Err(Error::UnexpectedEof(self.peek().span.clone()));
+            }
+            self.recovering(Self::declaration);
+        };
+        self.nesting -= 1;
+        closed
+    }
 
-        Ok(Decl::Declaration(ident, Some(expr)))
+    fn statement(&mut self) -> Result<(), Error> {
+        use TokenType::*;
+        let kind = match self.peek().token_type {
+            LeftBrace => SyntaxKind::Block,
+            If => SyntaxKind::IfStmt,
+            While => SyntaxKind::WhileStmt,
+            For => SyntaxKind::ForStmt,
+            Break => SyntaxKind::BreakStmt,
+            Continue => SyntaxKind::ContinueStmt,
+            Return => SyntaxKind::ReturnStmt,
+            Print => SyntaxKind::PrintStmt,
+            _ => SyntaxKind::ExprStmt,
+        };
+        self.node(kind, Self::unwrapped_statement)
     }
 
-    fn statement(&mut self) -> Result<Stmt, Error> {
+    /// Parses a statement without grouping it into a syntax tree node.
+    fn unwrapped_statement(&mut self) -> Result<(), Error> {
         use TokenType::*;
-        match self.advance_if(|t| [Print, LeftBrace, If].contains(&t.token_type)) {
-            Some(tok) if tok.token_type == LeftBrace => {
-                let mut decls = vec![];
-                while self.advance_if(|t| t.token_type == RightBrace).is_none() {
-                    if self.is_eof() {
-                        return Err(Error::UnexpectedEof(self.peek().line));
+        let keyword = self
+            .advance_if(|t| [Print, LeftBrace, If, While, For, Break, Continue, Return].contains(&t.token_type))
+            .map(|t| t.token_type.clone());
+        match keyword {
+            Some(LeftBrace) => self.block(),
+            Some(If) => {
+                self.expect(LeftParen, "(")?;
+                self.expression()?;
+                self.expect(RightParen, ")")?;
+                self.statement()?;
+                if self.advance_if(|t| t.token_type == Else).is_some() {
+                    self.statement()?;
+                }
+                Ok(())
+            }
+            Some(While) => {
+                self.expect(LeftParen, "(")?;
+                self.expression()?;
+                self.expect(RightParen, ")")?;
+                self.loop_body()
+            }
+            Some(For) => {
+                self.expect(LeftParen, "(")?;
+                match self.peek().token_type {
+                    SemiColon => {
+                        self.advance();
                     }
-                    let decl = self.declaration()?;
-                    decls.push(decl);
+                    Var => {
+                        self.declaration()?;
+                        if self.previous().token_type != SemiColon {
+                            self.expect(SemiColon, ";")?;
+                        }
+                    }
+                    _ => self.node(SyntaxKind::ExprStmt, |this| {
+                        this.expression()?;
+                        this.expect(SemiColon, ";").map(|_| ())
+                    })?,
+                }
+                if self.peek().token_type != SemiColon {
+                    self.expression()?;
                 }
-                Ok(Stmt::Block(decls))
+                self.expect(SemiColon, ";")?;
+                if self.peek().token_type != RightParen {
+                    self.expression()?;
+                }
+                self.expect(RightParen, ")")?;
+                self.loop_body()
             }
-            Some(tok) if tok.token_type == If => {
-                let tok = tok.clone();
-                let Some(_) = self.advance_if(|t| t.token_type == LeftParen) else {
-                    return Error::WrongTokenType(
-                        self.peek().line,
-                        self.peek().lexeme.clone(),
-                        "(".to_string(),
-                    )
-                    .to_result();
-                };
-                let expr = self.expression()?;
-                let Some(_) = self.advance_if(|t| t.token_type == RightParen) else {
-                    return Error::WrongTokenType(
-                        self.peek().line,
-                        self.peek().lexeme.clone(),
-                        ")".to_string(),
-                    )
-                    .to_result();
-                };
-                let stmt = self.statement()?;
-                let Some(_) = self.advance_if(|t| t.token_type == Else) else {
-                    return Ok(Stmt::If(tok.clone(), expr, Box::from(stmt), None));
-                };
-                let else_stmt = self.statement()?;
-
-                Ok(Stmt::If(
-                    tok.clone(),
-                    expr,
-                    Box::from(stmt),
-                    Some(Box::from(else_stmt)),
-                ))
+            Some(Break | Continue) => {
+                if self.loop_depth == 0 {
+                    let tok = self.previous();
+                    return Error::OutsideLoop(tok.span.clone(), tok.lexeme.clone()).to_result();
+                }
+                self.advance_if(|t| t.token_type == SemiColon);
+                Ok(())
             }
-            Some(tok) if tok.token_type == Print => {
-                let expr = self.expression()?;
+            Some(Return) => {
+                if !matches!(self.peek().token_type, SemiColon | RightBrace | Eof) {
+                    self.expression()?;
+                }
                 self.advance_if(|t| t.token_type == SemiColon);
-                Ok(Stmt::Print(expr))
+                Ok(())
             }
+            // A `print` statement, or an expression statement.
             _ => {
-                let expr = self.expression()?;
+                self.expression()?;
                 self.advance_if(|t| t.token_type == SemiColon);
-                Ok(Stmt::Expression(expr))
+                Ok(())
             }
         }
     }
 
-    fn expression(&mut self) -> Result<Expr, Error> {
-        self.assignment()
+    fn loop_body(&mut self) -> Result<(), Error> {
+        self.loop_depth += 1;
+        let body = self.statement();
+        self.loop_depth -= 1;
+        body
     }
 
-    fn assignment(&mut self) -> Result<Expr, Error> {
-        let mut expr = self.or()?;
+    fn expression(&mut self) -> Result<(), Error> {
+        self.assignment()
+    }
 
-        let Some(tok) = self.advance_if(|t| t.token_type == TokenType::Equal) else {
-            return Ok(expr);
+    fn assignment(&mut self) -> Result<(), Error> {
+        use TokenType::*;
+        let checkpoint = self.builder.checkpoint();
+        let start = self.cursor as usize;
+        self.ternary()?;
+        let target = self.builder.last_kind();
+        let end = self.cursor as usize;
+
+        let Some(tok) = self.advance_if(|t| {
+            [Equal, PlusEqual, MinusEqual, StarEqual, SlashEqual, PercentEqual].contains(&t.token_type)
+        }) else {
+            return Ok(());
         };
-        let tok = tok.clone();
+        let span = tok.span.clone();
 
-        let value = self.assignment()?;
+        self.assignment()?;
 
-        match expr {
-            Expr::Identifier(tok) => expr = Expr::Assign(tok, Box::from(value)),
-            _ => {
-                let mut printer = AstPrinter::default();
-                let expr = expr.accept(&mut printer).unwrap();
-                return Error::InvalidAssignmentTarget(tok.line, expr).to_result();
-            }
+        if !matches!(target, Some(SyntaxKind::Name | SyntaxKind::GetExpr | SyntaxKind::IndexExpr)) {
+            let target = syntax::source_of(&self.green[start..end]);
+            return Error::InvalidAssignmentTarget(span, target).to_result();
         }
+        self.builder.wrap(checkpoint, SyntaxKind::AssignExpr);
 
-        Ok(expr)
+        Ok(())
     }
 
-    fn or(&mut self) -> Result<Expr, Error> {
-        let mut expr = self.and()?;
+    fn ternary(&mut self) -> Result<(), Error> {
+        let checkpoint = self.builder.checkpoint();
+        self.or()?;
 
-        while let Some(tok) = self.advance_if(|t| t.token_type == TokenType::Or) {
-            let tok = tok.clone();
-            let right = self.and()?;
-            expr = Expr::Logical(Box::from(expr), tok, Box::from(right));
+        if self.advance_if(|t| t.token_type == TokenType::Question).is_none() {
+            return Ok(());
         }
+        self.expression()?;
+        self.expect(TokenType::Colon, ":")?;
+        self.ternary()?;
+        self.builder.wrap(checkpoint, SyntaxKind::TernaryExpr);
 
-        Ok(expr)
+        Ok(())
     }
 
-    fn and(&mut self) -> Result<Expr, Error> {
-        let mut expr = self.equality()?;
+    /// Parses `operand`s joined by any of the left-associative `operators`.
+    fn binary(&mut self, operators: &[TokenType], operand: fn(&mut Self) -> Result<(), Error>) -> Result<(), Error> {
+        let checkpoint = self.builder.checkpoint();
+        operand(self)?;
 
-        while let Some(tok) = self.advance_if(|t| t.token_type == TokenType::And) {
-            let tok = tok.clone();
-            let right = self.equality()?;
-            expr = Expr::Logical(Box::from(expr), tok, Box::from(right));
+        while self.advance_if(|t| operators.contains(&t.token_type)).is_some() {
+            operand(self)?;
+            self.builder.wrap(checkpoint, SyntaxKind::BinaryExpr);
         }
 
-        Ok(expr)
+        Ok(())
     }
 
-    fn equality(&mut self) -> Result<Expr, Error> {
-        use TokenType::*;
-        let mut expr = self.comparison()?;
+    fn or(&mut self) -> Result<(), Error> {
+        self.binary(&[TokenType::Or], Self::and)
+    }
 
-        while let Some(tok) = self.advance_if(|t| [BangEqual, EqualEqual].contains(&t.token_type)) {
-            let tok = tok.clone();
-            let right = self.comparison()?;
-            expr = Expr::Binary(Box::from(expr), tok, Box::from(right));
-        }
+    fn and(&mut self) -> Result<(), Error> {
+        self.binary(&[TokenType::And], Self::equality)
+    }
 
-        Ok(expr)
+    fn equality(&mut self) -> Result<(), Error> {
+        self.binary(&[TokenType::BangEqual, TokenType::EqualEqual], Self::comparison)
     }
 
-    fn comparison(&mut self) -> Result<Expr, Error> {
+    fn comparison(&mut self) -> Result<(), Error> {
         use TokenType::*;
-        let mut expr = self.term()?;
+        self.binary(&[Greater, GreaterEqual, Less, LessEqual], Self::bitwise_or)
+    }
 
-        while let Some(tok) =
-            self.advance_if(|t| [Greater, GreaterEqual, Less, LessEqual].contains(&t.token_type))
-        {
-            let tok = tok.clone();
-            let right = self.term()?;
-            expr = Expr::Binary(Box::from(expr), tok, Box::from(right));
-        }
+    fn bitwise_or(&mut self) -> Result<(), Error> {
+        self.binary(&[TokenType::Pipe], Self::bitwise_xor)
+    }
+
+    fn bitwise_xor(&mut self) -> Result<(), Error> {
+        self.binary(&[TokenType::Caret], Self::bitwise_and)
+    }
+
+    fn bitwise_and(&mut self) -> Result<(), Error> {
+        self.binary(&[TokenType::Ampersand], Self::shift)
+    }
+
+    fn shift(&mut self) -> Result<(), Error> {
+        self.binary(&[TokenType::LessLess, TokenType::GreaterGreater], Self::term)
+    }
 
-        Ok(expr)
+    fn term(&mut self) -> Result<(), Error> {
+        self.binary(&[TokenType::Plus, TokenType::Minus], Self::factor)
     }
 
-    fn term(&mut self) -> Result<Expr, Error> {
+    fn factor(&mut self) -> Result<(), Error> {
         use TokenType::*;
-        let mut expr = self.factor()?;
+        self.binary(&[Star, Slash, Percent], Self::unary)
+    }
 
-        while let Some(tok) = self.advance_if(|t| [Plus, Minus].contains(&t.token_type)) {
-            let tok = tok.clone();
-            let right = self.factor()?;
-            expr = Expr::Binary(Box::from(expr), tok, Box::from(right));
+    fn unary(&mut self) -> Result<(), Error> {
+        use TokenType::*;
+        let checkpoint = self.builder.checkpoint();
+        match self.advance_if(|t| [Minus, Bang, Tilde].contains(&t.token_type)) {
+            Some(_) => {
+                self.unary()?;
+                self.builder.wrap(checkpoint, SyntaxKind::UnaryExpr);
+                Ok(())
+            }
+            _ => self.exponent(),
         }
+    }
+
+    /// Exponentiation binds tighter than unary operators on its left, as in
+    /// `-2 ** 2 == -4`, and is right-associative.
+    fn exponent(&mut self) -> Result<(), Error> {
+        let checkpoint = self.builder.checkpoint();
+        self.call()?;
 
-        Ok(expr)
+        if self.advance_if(|t| t.token_type == TokenType::StarStar).is_some() {
+            self.unary()?;
+            self.builder.wrap(checkpoint, SyntaxKind::BinaryExpr);
+        }
+        Ok(())
     }
 
-    fn factor(&mut self) -> Result<Expr, Error> {
+    fn call(&mut self) -> Result<(), Error> {
         use TokenType::*;
-        let mut expr = self.unary()?;
+        let checkpoint = self.builder.checkpoint();
+        self.primary()?;
 
-        while let Some(tok) = self.advance_if(|t| [Star, Slash].contains(&t.token_type)) {
-            let tok = tok.clone();
-            let right = self.unary()?;
-            expr = Expr::Binary(Box::from(expr), tok, Box::from(right));
+        while let Some(tok) = self
+            .advance_if(|t| [LeftParen, Dot, LeftBracket].contains(&t.token_type))
+            .map(|t| t.token_type.clone())
+        {
+            match tok {
+                Dot => {
+                    self.expect(Identifier, "property name")?;
+                    self.builder.wrap(checkpoint, SyntaxKind::GetExpr);
+                }
+                LeftBracket => {
+                    self.expression()?;
+                    self.expect(RightBracket, "]")?;
+                    self.builder.wrap(checkpoint, SyntaxKind::IndexExpr);
+                }
+                _ => {
+                    self.arguments(RightParen)?;
+                    self.expect(RightParen, ")")?;
+                    self.builder.wrap(checkpoint, SyntaxKind::CallExpr);
+                }
+            }
         }
 
-        Ok(expr)
+        Ok(())
     }
 
-    fn unary(&mut self) -> Result<Expr, Error> {
-        use TokenType::*;
-        match self.advance_if(|t| [Minus, Bang].contains(&t.token_type)) {
-            Some(tok) => {
-                let tok = tok.clone();
-                let right = self.unary()?;
-                Ok(Expr::Unary(tok, Box::from(right)))
+    /// Parses comma-separated expressions up to, but not including, `end`.
+    /// A trailing comma is allowed after at least one expression.
+    fn arguments(&mut self, end: TokenType) -> Result<(), Error> {
+        while self.peek().token_type != end {
+            self.expression()?;
+            if self.advance_if(|t| t.token_type == TokenType::Comma).is_none() {
+                break;
             }
-            _ => self.primary(),
         }
+        Ok(())
     }
 
-    fn primary(&mut self) -> Result<Expr, Error> {
+    fn primary(&mut self) -> Result<(), Error> {
         if self.is_eof() {
-            return Error::UnexpectedEof(self.peek().line).to_result();
+            return Error::UnexpectedEof(self.peek().span.clone()).to_result();
         }
 
+        let kind = match self.peek().token_type {
+            TokenType::Identifier => SyntaxKind::Name,
+            TokenType::Interpolation => SyntaxKind::InterpolationExpr,
+            TokenType::Fun | TokenType::Fn => SyntaxKind::LambdaExpr,
+            TokenType::This => SyntaxKind::ThisExpr,
+            TokenType::LeftBracket => SyntaxKind::ListExpr,
+            TokenType::LeftBrace => SyntaxKind::MapExpr,
+            TokenType::Super => SyntaxKind::SuperExpr,
+            TokenType::LeftParen => SyntaxKind::GroupingExpr,
+            _ => SyntaxKind::Literal,
+        };
+        self.node(kind, Self::unwrapped_primary)
+    }
+
+    /// Parses a primary expression without grouping it into a syntax tree
+    /// node.
+    fn unwrapped_primary(&mut self) -> Result<(), Error> {
         match self.peek().token_type {
             TokenType::True
             | TokenType::False
             | TokenType::Nil
             | TokenType::Integer
             | TokenType::Float
-            | TokenType::String => Ok(Expr::Literal(self.advance().clone())),
-            TokenType::Identifier => Ok(Expr::Identifier(self.advance().clone())),
-            TokenType::LeftParen => {
+            | TokenType::String
+            | TokenType::Identifier
+            | TokenType::This => {
                 self.advance();
-                let expr = self.expression()?;
-                match self.peek().token_type {
-                    TokenType::RightParen => {
-                        self.advance();
-                        Ok(Expr::Grouping(Box::from(expr)))
-                    }
-                    _ => {
-                        let token = self.peek();
-                        Error::WrongTokenType(
-                            token.clone().line,
-                            token.clone().lexeme,
-                            ")".to_string(),
-                        )
-                        .to_result()
+                Ok(())
+            }
+            // Stands in for whatever failed to tokenize, which has already
+            // been reported.
+            TokenType::Invalid => {
+                self.advance();
+                Ok(())
+            }
+            TokenType::Interpolation => {
+                self.advance();
+                loop {
+                    self.expression()?;
+                    match self.peek().token_type {
+                        TokenType::InterpolationMiddle => {
+                            self.advance();
+                        }
+                        TokenType::InterpolationEnd => {
+                            self.advance();
+                            return Ok(());
+                        }
+                        _ => return self.unexpected("}").to_result(),
                     }
                 }
             }
-            _ => {
+            // The rest of a string can only follow an interpolated
+            // expression, so the expression is missing or incomplete.
+            TokenType::InterpolationMiddle | TokenType::InterpolationEnd => {
                 let token = self.peek();
-                Error::WrongTokenType(token.clone().line, token.clone().lexeme, "(".to_string())
-                    .to_result()
+                let brace = Span {
+                    end: token.span.start + 1,
+                    end_line: token.span.line,
+                    end_column: token.span.column + 1,
+                    ..token.span.clone()
+                };
+                Error::WrongTokenType(brace, "}".to_string(), "expression".to_string()).to_result()
+            }
+            TokenType::Fun | TokenType::Fn => {
+                self.advance();
+                self.function()
+            }
+            TokenType::LeftBracket => {
+                self.advance();
+                self.arguments(TokenType::RightBracket)?;
+                self.expect(TokenType::RightBracket, "]").map(|_| ())
+            }
+            // A brace only opens a block at the start of a statement, so here
+            // it can only be a map literal.
+            TokenType::LeftBrace => {
+                self.advance();
+                while self.peek().token_type != TokenType::RightBrace {
+                    self.expression()?;
+                    self.expect(TokenType::Colon, ":")?;
+                    self.expression()?;
+                    if self.advance_if(|t| t.token_type == TokenType::Comma).is_none() {
+                        break;
+                    }
+                }
+                self.expect(TokenType::RightBrace, "}").map(|_| ())
+            }
+            TokenType::Super => {
+                self.advance();
+                self.expect(TokenType::Dot, ".")?;
+                self.expect(TokenType::Identifier, "superclass method name").map(|_| ())
             }
+            TokenType::LeftParen => {
+                self.advance();
+                self.expression()?;
+                self.expect(TokenType::RightParen, ")").map(|_| ())
+            }
+            _ => self.unexpected("expression").to_result(),
         }
     }
 
@@ -312,17 +633,35 @@ impl Parser {
     }
 
     fn shift_cursor(&mut self, offset: i32) {
-        self.cursor = self.cursor + offset;
+        self.cursor += offset;
     }
 
     fn advance(&mut self) -> &Token {
         if !self.is_eof() {
+            self.builder.token(self.green[self.cursor as usize].clone());
             self.shift_cursor(1);
         }
 
         self.previous()
     }
 
+    fn expect(&mut self, token_type: TokenType, expected: &str) -> Result<&Token, Error> {
+        if self.peek().token_type == token_type {
+            return Ok(self.advance());
+        }
+        self.unexpected(expected).to_result()
+    }
+
+    /// The error for finding the next token where `expected` should be.
+    fn unexpected(&self, expected: &str) -> Error {
+        let token = self.peek();
+        let found = match token.token_type {
+            TokenType::Eof => "end of input".to_string(),
+            _ => token.lexeme.clone(),
+        };
+        Error::WrongTokenType(token.span.clone(), found, expected.to_string())
+    }
+
     fn advance_if<F>(&mut self, f: F) -> Option<&Token>
     where
         F: FnOnce(&Token) -> bool,
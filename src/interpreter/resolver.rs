@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+
+use super::{
+    error::Error,
+    expression::{Decl, Depth, Expr, Function, Stmt, Visitor},
+    token::Token,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionKind {
+    None,
+    Function,
+    Method,
+    Initializer,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ClassKind {
+    None,
+    Class,
+    Subclass,
+}
+
+/// Static pass run between parsing and evaluation.
+///
+/// Records on every local variable reference how many scopes out its
+/// declaration is, and reports misuse of bindings that can be detected
+/// without running the program.
+pub struct Resolver {
+    /// Local scopes, innermost last, mapping each name to whether its
+    /// initializer has finished. Globals are not tracked.
+    scopes: Vec<HashMap<String, bool>>,
+    function: FunctionKind,
+    class: ClassKind,
+    errors: Vec<Error>,
+}
+
+impl Resolver {
+    pub fn resolve(decls: &[Decl]) -> Result<(), Vec<Error>> {
+        let mut resolver = Self {
+            scopes: Vec::default(),
+            function: FunctionKind::None,
+            class: ClassKind::None,
+            errors: Vec::default(),
+        };
+
+        for decl in decls.iter() {
+            let _ = decl.accept(&mut resolver);
+        }
+
+        match resolver.errors.is_empty() {
+            true => Ok(()),
+            false => Err(resolver.errors),
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::default());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+        if scope.contains_key(&name.lexeme) {
+            self.errors
+                .push(Error::AlreadyDeclared(name.span.clone(), name.lexeme.clone()));
+        }
+        scope.insert(name.lexeme.clone(), false);
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), true);
+        }
+    }
+
+    fn resolve_local(&mut self, name: &str, depth: &Depth) {
+        if let Some(d) = self
+            .scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(name))
+        {
+            depth.set(d);
+        }
+    }
+
+    /// Resolves a read of the variable `name`, which cannot happen in its
+    /// own initializer.
+    fn resolve_read(&mut self, name: &Token, depth: &Depth) {
+        if let Some(false) = self.scopes.last().and_then(|s| s.get(&name.lexeme)) {
+            self.errors
+                .push(Error::ReadInOwnInitializer(name.span.clone(), name.lexeme.clone()));
+        }
+        self.resolve_local(&name.lexeme, depth);
+    }
+
+    fn resolve_function(&mut self, function: &Function, kind: FunctionKind) {
+        let enclosing = std::mem::replace(&mut self.function, kind);
+
+        // Parameters share a scope with the body, as they do at runtime.
+        self.begin_scope();
+        for param in function.params.iter() {
+            self.declare(param);
+            self.define(param);
+        }
+        for decl in function.body.iter() {
+            let _ = decl.accept(self);
+        }
+        self.end_scope();
+
+        self.function = enclosing;
+    }
+}
+
+impl Visitor<(), Decl> for Resolver {
+    fn visit(&mut self, decl: &Decl) -> Result<(), Error> {
+        match decl {
+            Decl::Declaration(_, name, init) => {
+                self.declare(name);
+                if let Some(init) = init {
+                    init.accept(self)?;
+                }
+                self.define(name);
+            }
+            Decl::Function(f) => {
+                if let Some(name) = &f.name {
+                    self.declare(name);
+                    self.define(name);
+                }
+                self.resolve_function(f, FunctionKind::Function);
+            }
+            Decl::Class(_, name, superclass, methods) => {
+                let enclosing = std::mem::replace(&mut self.class, ClassKind::Class);
+                self.declare(name);
+                self.define(name);
+
+                if let Some(superclass) = superclass {
+                    if let Expr::Identifier(super_name, _) = superclass {
+                        if super_name.lexeme == name.lexeme {
+                            self.errors
+                                .push(Error::InheritsFromSelf(name.span.clone(), name.lexeme.clone()));
+                        }
+                    }
+                    self.class = ClassKind::Subclass;
+                    superclass.accept(self)?;
+
+                    // Methods close over a scope binding `super`, which in turn
+                    // encloses the scope binding `this` when they are bound.
+                    self.begin_scope();
+                    self.scopes
+                        .last_mut()
+                        .unwrap()
+                        .insert("super".to_string(), true);
+                }
+
+                self.begin_scope();
+                self.scopes
+                    .last_mut()
+                    .unwrap()
+                    .insert("this".to_string(), true);
+                for method in methods.iter() {
+                    let kind = match method.name.as_ref() {
+                        Some(name) if name.lexeme == "init" => FunctionKind::Initializer,
+                        _ => FunctionKind::Method,
+                    };
+                    self.resolve_function(method, kind);
+                }
+                self.end_scope();
+
+                if superclass.is_some() {
+                    self.end_scope();
+                }
+                self.class = enclosing;
+            }
+            Decl::Statement(s) => s.accept(self)?,
+        }
+        Ok(())
+    }
+}
+
+impl Visitor<(), Stmt> for Resolver {
+    fn visit(&mut self, stmt: &Stmt) -> Result<(), Error> {
+        match stmt {
+            Stmt::Expression(_, e) | Stmt::Print(_, e) => e.accept(self)?,
+            Stmt::If(_, cond, then, otherwise) => {
+                cond.accept(self)?;
+                then.accept(self)?;
+                if let Some(otherwise) = otherwise {
+                    otherwise.accept(self)?;
+                }
+            }
+            Stmt::Block(_, decls) => {
+                self.begin_scope();
+                for decl in decls.iter() {
+                    decl.accept(self)?;
+                }
+                self.end_scope();
+            }
+            Stmt::While(_, cond, body) => {
+                cond.accept(self)?;
+                body.accept(self)?;
+            }
+            Stmt::For(_, init, cond, incr, body) => {
+                self.begin_scope();
+                if let Some(init) = init {
+                    init.accept(self)?;
+                }
+                if let Some(cond) = cond {
+                    cond.accept(self)?;
+                }
+                if let Some(incr) = incr {
+                    incr.accept(self)?;
+                }
+                body.accept(self)?;
+                self.end_scope();
+            }
+            Stmt::Break(..) | Stmt::Continue(..) => {}
+            Stmt::Return(_, tok, value) => {
+                if self.function == FunctionKind::None {
+                    self.errors.push(Error::ReturnOutsideFunction(tok.span.clone()));
+                }
+                if let Some(value) = value {
+                    if self.function == FunctionKind::Initializer {
+                        self.errors.push(Error::ReturnValueFromInitializer(tok.span.clone()));
+                    }
+                    value.accept(self)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Visitor<(), Expr> for Resolver {
+    fn visit(&mut self, expr: &Expr) -> Result<(), Error> {
+        match expr {
+            Expr::Identifier(name, depth) => self.resolve_read(name, depth),
+            Expr::Assign(name, op, value, depth) => {
+                value.accept(self)?;
+                // A compound assignment reads the variable as well.
+                match op {
+                    Some(_) => self.resolve_read(name, depth),
+                    None => self.resolve_local(&name.lexeme, depth),
+                }
+            }
+            Expr::Logical(left, _, right) | Expr::Binary(left, _, right) => {
+                left.accept(self)?;
+                right.accept(self)?;
+            }
+            Expr::Ternary(condition, _, then, otherwise) => {
+                condition.accept(self)?;
+                then.accept(self)?;
+                otherwise.accept(self)?;
+            }
+            Expr::Grouping(_, e) | Expr::Unary(_, e) => e.accept(self)?,
+            Expr::Literal(_) => {}
+            Expr::Interpolation(_, parts) => {
+                for part in parts.iter() {
+                    part.accept(self)?;
+                }
+            }
+            Expr::Call(callee, _, args) => {
+                callee.accept(self)?;
+                for arg in args.iter() {
+                    arg.accept(self)?;
+                }
+            }
+            Expr::List(_, items) => {
+                for item in items.iter() {
+                    item.accept(self)?;
+                }
+            }
+            Expr::Map(_, entries) => {
+                for (key, value) in entries.iter() {
+                    key.accept(self)?;
+                    value.accept(self)?;
+                }
+            }
+            Expr::Index(list, _, index) => {
+                list.accept(self)?;
+                index.accept(self)?;
+            }
+            Expr::SetIndex(list, _, index, _, value) => {
+                value.accept(self)?;
+                list.accept(self)?;
+                index.accept(self)?;
+            }
+            Expr::Lambda(f) => self.resolve_function(f, FunctionKind::Function),
+            Expr::Get(object, _) => object.accept(self)?,
+            Expr::Set(object, _, _, value) => {
+                value.accept(self)?;
+                object.accept(self)?;
+            }
+            Expr::This(keyword, depth) => match self.class {
+                ClassKind::None => self.errors.push(Error::ThisOutsideClass(keyword.span.clone())),
+                _ => self.resolve_local(&keyword.lexeme, depth),
+            },
+            Expr::Super(keyword, _, depth) => match self.class {
+                ClassKind::Subclass => self.resolve_local(&keyword.lexeme, depth),
+                _ => self.errors.push(Error::SuperOutsideSubclass(keyword.span.clone())),
+            },
+        }
+        Ok(())
+    }
+}
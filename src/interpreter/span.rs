@@ -0,0 +1,43 @@
+use std::fmt::Display;
+use std::rc::Rc;
+
+/// A range of source code.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Span {
+    /// The name of the file the source was read from, such as `<eval>` when
+    /// it did not come from a file.
+    pub file: Rc<str>,
+    /// Byte offset of the first character.
+    pub start: usize,
+    /// Byte offset just past the last character.
+    pub end: usize,
+    /// Line of the first character, counting from 1.
+    pub line: i32,
+    /// Column of the first character in its line, counting characters from 1.
+    pub column: i32,
+    /// Line of the position just past the last character.
+    pub end_line: i32,
+    /// Column of the position just past the last character.
+    pub end_column: i32,
+}
+
+impl Span {
+    /// The span from the start of this one to the end of `end`.
+    pub fn to(&self, end: &Span) -> Span {
+        match end.end < self.start {
+            true => self.clone(),
+            false => Span {
+                end: end.end,
+                end_line: end.end_line,
+                end_column: end.end_column,
+                ..self.clone()
+            },
+        }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
@@ -0,0 +1,48 @@
+/// Finds the candidate closest to the misspelled `name`, if any is close
+/// enough to likely be what was meant.
+///
+/// Closeness is the number of characters inserted, removed, replaced or
+/// swapped with their neighbour to turn one into the other. Ties go to the
+/// earliest candidate.
+pub(crate) fn did_you_mean<'a, I>(name: &str, candidates: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let name = name.chars().collect::<Vec<char>>();
+    // Some of `name` has to be left unedited, or a one letter name would
+    // match every other.
+    let max_distance = (name.len() / 3).max(1).min(name.len().saturating_sub(1));
+
+    candidates
+        .into_iter()
+        .map(|candidate| (distance(&name, &candidate.chars().collect::<Vec<char>>()), candidate))
+        .filter(|(distance, _)| *distance > 0 && *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The optimal string alignment distance between `a` and `b`.
+fn distance(a: &[char], b: &[char]) -> usize {
+    // `rows[i][j]` is the distance between the first `i` characters of `a`
+    // and the first `j` of `b`.
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
@@ -0,0 +1,423 @@
+use std::fmt::Display;
+use std::ops::Range;
+use std::rc::Rc;
+
+use super::{error::Error, expression::Decl, lower::Lowering, parser::Parser, token::*};
+
+/// The kind of construct a [`SyntaxNode`] holds the source of.
+///
+/// Operators, keywords and punctuation are tokens directly inside the node
+/// of the construct they belong to, and operands are child nodes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyntaxKind {
+    /// The whole source, ending with the `Eof` token.
+    Script,
+    /// Source skipped over after a syntax error.
+    Error,
+    /// A named function, or a method of a class.
+    FunctionDecl,
+    ClassDecl,
+    VarDecl,
+    /// The parenthesized parameters of a function.
+    ParamList,
+    ExprStmt,
+    PrintStmt,
+    /// A block statement, or the body of a function.
+    Block,
+    IfStmt,
+    WhileStmt,
+    ForStmt,
+    BreakStmt,
+    ContinueStmt,
+    ReturnStmt,
+    /// An assignment, compound or not, to a variable, property or item.
+    AssignExpr,
+    TernaryExpr,
+    /// An infix operator, including the logical `and` and `or`.
+    BinaryExpr,
+    UnaryExpr,
+    CallExpr,
+    GetExpr,
+    IndexExpr,
+    Literal,
+    /// A variable read.
+    Name,
+    ThisExpr,
+    SuperExpr,
+    LambdaExpr,
+    ListExpr,
+    MapExpr,
+    GroupingExpr,
+    /// A string with interpolated expressions, whose literal parts are its
+    /// `Interpolation`, `InterpolationMiddle` and `InterpolationEnd` tokens.
+    InterpolationExpr,
+}
+
+/// Whitespace or a comment, attached to the token before or after it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trivia {
+    pub kind: TokenType,
+    pub text: String,
+}
+
+/// A token along with its trivia, without a position, so that it can be
+/// shared between trees.
+#[derive(Debug)]
+pub(crate) struct GreenToken {
+    kind: TokenType,
+    text: String,
+    leading: Vec<Trivia>,
+    trailing: Vec<Trivia>,
+}
+
+impl GreenToken {
+    fn len(&self) -> usize {
+        let trivia = |trivia: &[Trivia]| trivia.iter().map(|t| t.text.len()).sum::<usize>();
+        trivia(&self.leading) + self.text.len() + trivia(&self.trailing)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct GreenNode {
+    kind: SyntaxKind,
+    /// The length of the node's source, trivia included.
+    len: usize,
+    children: Vec<GreenElement>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    fn len(&self) -> usize {
+        match self {
+            Self::Node(node) => node.len,
+            Self::Token(token) => token.len(),
+        }
+    }
+}
+
+/// Attaches the trivia among `tokens`, lexed from `source`, to the tokens
+/// the parser reads, returning those along with their tree tokens.
+///
+/// A token keeps the trivia after it up to the end of its line. Any other
+/// trivia, such as a comment on a line of its own, leads the next token.
+pub(crate) fn attach_trivia(tokens: Vec<Token>, source: &str) -> (Vec<Token>, Vec<Rc<GreenToken>>) {
+    let mut significant: Vec<Token> = vec![];
+    let mut green: Vec<GreenToken> = vec![];
+    let mut leading = vec![];
+    let mut trailing = false;
+    for token in tokens {
+        // The `Eof` token's lexeme is not in the source.
+        let text = match token.token_type {
+            TokenType::Eof => String::new(),
+            _ => source[token.span.start..token.span.end].to_string(),
+        };
+        if !token.token_type.is_trivia() {
+            green.push(GreenToken {
+                kind: token.token_type.clone(),
+                text,
+                leading: std::mem::take(&mut leading),
+                trailing: vec![],
+            });
+            significant.push(token);
+            trailing = true;
+            continue;
+        }
+        let ends_line = text.contains('\n');
+        let trivia = Trivia {
+            kind: token.token_type,
+            text,
+        };
+        match green.last_mut() {
+            Some(last) if trailing => last.trailing.push(trivia),
+            _ => leading.push(trivia),
+        }
+        trailing &= !ends_line;
+    }
+    (significant, green.into_iter().map(Rc::new).collect())
+}
+
+/// The source of `tokens` on one line, with any trivia between two of them
+/// reduced to a space.
+pub(crate) fn source_of(tokens: &[Rc<GreenToken>]) -> String {
+    let mut source = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if i > 0 && !(tokens[i - 1].trailing.is_empty() && token.leading.is_empty()) {
+            source.push(' ');
+        }
+        source.push_str(&token.text);
+    }
+    source
+}
+
+/// Builds the green tree of a source from the tokens the parser reads, in
+/// order, grouping them into nodes once the construct they make up has been
+/// parsed.
+#[derive(Default)]
+pub(crate) struct TreeBuilder {
+    children: Vec<GreenElement>,
+}
+
+impl TreeBuilder {
+    pub fn token(&mut self, token: Rc<GreenToken>) {
+        self.children.push(GreenElement::Token(token));
+    }
+
+    /// Marks where a node about to be parsed starts.
+    pub fn checkpoint(&self) -> usize {
+        self.children.len()
+    }
+
+    /// The kind of the node added last, unless a token was added after it.
+    pub fn last_kind(&self) -> Option<SyntaxKind> {
+        match self.children.last()? {
+            GreenElement::Node(node) => Some(node.kind),
+            GreenElement::Token(_) => None,
+        }
+    }
+
+    /// Groups everything added since `checkpoint` into a `kind` node.
+    pub fn wrap(&mut self, checkpoint: usize, kind: SyntaxKind) {
+        let children = self.children.split_off(checkpoint);
+        self.children.push(GreenElement::Node(Rc::new(GreenNode {
+            kind,
+            len: children.iter().map(GreenElement::len).sum(),
+            children,
+        })));
+    }
+
+    pub fn finish(mut self) -> SyntaxNode {
+        self.wrap(0, SyntaxKind::Script);
+        let Some(GreenElement::Node(green)) = self.children.pop() else {
+            unreachable!("the script was just wrapped")
+        };
+        SyntaxNode(Rc::new(NodeData {
+            green,
+            offset: 0,
+            parent: None,
+        }))
+    }
+}
+
+/// A lossless syntax tree of a source, holding every character of it,
+/// whitespace and comments included.
+///
+/// ```
+/// # use rlox::{SyntaxKind, SyntaxTree};
+/// let source = "var x = 1 + 2; // three\n";
+/// let tree = SyntaxTree::parse(source, "x.lox");
+/// assert!(tree.errors().is_empty());
+/// assert_eq!(tree.root().to_string(), source);
+///
+/// let decl = tree.root().child_nodes().next().unwrap();
+/// assert_eq!(decl.kind(), SyntaxKind::VarDecl);
+/// let semicolon = decl.tokens().pop().unwrap();
+/// assert_eq!((semicolon.text(), semicolon.range()), (";", 13..14));
+/// assert_eq!(semicolon.trailing()[1].text, "// three");
+/// ```
+pub struct SyntaxTree {
+    root: SyntaxNode,
+    decls: Vec<Decl>,
+    errors: Vec<Error>,
+}
+
+impl SyntaxTree {
+    /// Tokenizes and parses `source`, read from `file`.
+    ///
+    /// The tree covers all of `source` even when it fails to tokenize or
+    /// parse, with what was skipped after each syntax error in an `Error`
+    /// node.
+    pub fn parse(source: &str, file: &str) -> Self {
+        let (tokens, mut errors) = Token::tokenize(source.to_string(), file);
+        let (tokens, green) = attach_trivia(tokens, source);
+        let mut parser = Parser::new(tokens, green);
+        if let Err(parse_errors) = parser.parse() {
+            errors.extend(parse_errors);
+            errors.sort_by_key(|e| e.span().map(|span| span.start));
+        }
+        let (root, tokens) = parser.finish();
+        // Only a tree without syntax errors is complete enough to derive the
+        // AST from.
+        let decls = match errors.is_empty() {
+            true => Lowering::lower(&root, &tokens),
+            false => vec![],
+        };
+        Self { root, decls, errors }
+    }
+
+    pub fn root(&self) -> &SyntaxNode {
+        &self.root
+    }
+
+    /// The errors found tokenizing and parsing, in source order.
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    /// The declarations of the source, derived from the tree, or none if it
+    /// failed to tokenize or parse.
+    pub(crate) fn decls(&self) -> &[Decl] {
+        &self.decls
+    }
+
+    /// The declarations of the source, unless it failed to tokenize or parse.
+    pub(crate) fn into_decls(self) -> Result<Vec<Decl>, Vec<Error>> {
+        match self.errors.is_empty() {
+            true => Ok(self.decls),
+            false => Err(self.errors),
+        }
+    }
+}
+
+/// A node of a [`SyntaxTree`], which knows where it is in the source and
+/// which node it is in.
+#[derive(Clone, Debug)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+#[derive(Debug)]
+struct NodeData {
+    green: Rc<GreenNode>,
+    offset: usize,
+    parent: Option<SyntaxNode>,
+}
+
+/// A token of a [`SyntaxTree`], along with the trivia around it.
+#[derive(Clone, Debug)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    /// Where its leading trivia starts.
+    offset: usize,
+    parent: SyntaxNode,
+}
+
+#[derive(Clone, Debug)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind
+    }
+
+    /// The byte range of the node's source, trivia included.
+    pub fn range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.len
+    }
+
+    pub fn parent(&self) -> Option<&SyntaxNode> {
+        self.0.parent.as_ref()
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = SyntaxElement> + '_ {
+        self.0.green.children.iter().scan(self.0.offset, |offset, child| {
+            let element = match child {
+                GreenElement::Node(green) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                    green: green.clone(),
+                    offset: *offset,
+                    parent: Some(self.clone()),
+                }))),
+                GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                    green: green.clone(),
+                    offset: *offset,
+                    parent: self.clone(),
+                }),
+            };
+            *offset += child.len();
+            Some(element)
+        })
+    }
+
+    pub fn child_nodes(&self) -> impl Iterator<Item = SyntaxNode> + '_ {
+        self.children().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    pub fn child_tokens(&self) -> impl Iterator<Item = SyntaxToken> + '_ {
+        self.children().filter_map(|child| match child {
+            SyntaxElement::Node(_) => None,
+            SyntaxElement::Token(token) => Some(token),
+        })
+    }
+
+    /// Every token in the node, however deep, in source order.
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = vec![];
+        for child in self.children() {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+}
+
+impl Display for SyntaxNode {
+    /// Writes the node's source exactly as it was read.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for token in self.tokens() {
+            write!(f, "{token}")?;
+        }
+        Ok(())
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> &TokenType {
+        &self.green.kind
+    }
+
+    /// The token's source, without its trivia.
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    /// The byte range of the token's source, without its trivia.
+    pub fn range(&self) -> Range<usize> {
+        let start = self.offset + self.green.leading.iter().map(|t| t.text.len()).sum::<usize>();
+        start..start + self.green.text.len()
+    }
+
+    /// The trivia before the token that is not on the line of the previous
+    /// token.
+    pub fn leading(&self) -> &[Trivia] {
+        &self.green.leading
+    }
+
+    /// The trivia after the token up to the end of its line.
+    pub fn trailing(&self) -> &[Trivia] {
+        &self.green.trailing
+    }
+
+    pub fn parent(&self) -> &SyntaxNode {
+        &self.parent
+    }
+}
+
+impl Display for SyntaxToken {
+    /// Writes the token's source along with its trivia.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let GreenToken {
+            text,
+            leading,
+            trailing,
+            ..
+        } = &*self.green;
+        for trivia in leading.iter() {
+            write!(f, "{}", trivia.text)?;
+        }
+        write!(f, "{text}")?;
+        for trivia in trailing.iter() {
+            write!(f, "{}", trivia.text)?;
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
+use std::rc::Rc;
+
 use super::error::Error;
+use super::span::Span;
 use crate::prelude::*;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -8,33 +11,63 @@ pub enum TokenType {
 	RightParen,
 	LeftBrace,
 	RightBrace,
+	LeftBracket,
+	RightBracket,
+	Colon,
 	Comma,
 	Dot,
-	Minus,
-	Plus,
 	SemiColon,
-	Slash,
-	Star,
+	Question,
+	Ampersand,
+	Pipe,
+	Caret,
+	Tilde,
 
 	// One or two character tokens.
+	Slash,
+	SlashEqual,
+	Star,
+	StarEqual,
+	StarStar,
+	Percent,
+	PercentEqual,
+	Plus,
+	PlusEqual,
+	Minus,
+	MinusEqual,
 	Bang,
 	BangEqual,
 	Equal,
 	EqualEqual,
 	Greater,
 	GreaterEqual,
+	GreaterGreater,
 	Less,
 	LessEqual,
+	LessLess,
 
 	// Literals.
 	Identifier,
 	String,
+	/// The part of a string literal up to and including a `${` which starts
+	/// an interpolated expression. The rest of the string follows the
+	/// expression as an `InterpolationMiddle` or, for the last part, an
+	/// `InterpolationEnd`.
+	Interpolation,
+	/// The part of a string literal from the `}` closing an interpolated
+	/// expression up to and including the `${` starting the next one.
+	InterpolationMiddle,
+	/// The part of a string literal from the `}` closing its last
+	/// interpolated expression up to its end.
+	InterpolationEnd,
 	Integer,
 	Float,
 
 	// Keywords.
 	And,
+	Break,
 	Class,
+	Continue,
 	Else,
 	False,
 	Fun,
@@ -52,15 +85,55 @@ pub enum TokenType {
 	While,
 
 	SingleLineComment,
+	/// A `/* ... */` comment, which may contain nested block comments.
+	BlockComment,
+	/// A `///` comment documenting the declaration that follows it.
+	DocComment,
 	Whitespace,
+	/// Source that failed to tokenize, standing in for the token it was
+	/// meant to be so that the parser can carry on past it.
+	Invalid,
 	Eof,
 }
 
+/// The reserved words, which are never lexed as identifiers.
+pub const KEYWORDS: [(&str, TokenType); 19] = [
+	("and", TokenType::And),
+	("break", TokenType::Break),
+	("class", TokenType::Class),
+	("continue", TokenType::Continue),
+	("else", TokenType::Else),
+	("false", TokenType::False),
+	("for", TokenType::For),
+	("fun", TokenType::Fun),
+	("fn", TokenType::Fn),
+	("if", TokenType::If),
+	("nil", TokenType::Nil),
+	("or", TokenType::Or),
+	("print", TokenType::Print),
+	("return", TokenType::Return),
+	("super", TokenType::Super),
+	("this", TokenType::This),
+	("true", TokenType::True),
+	("var", TokenType::Var),
+	("while", TokenType::While),
+];
+
+impl TokenType {
+	/// Whether tokens of this type carry no meaning for the parser.
+	pub fn is_trivia(&self) -> bool {
+		matches!(
+			self,
+			Self::Whitespace | Self::SingleLineComment | Self::BlockComment | Self::DocComment
+		)
+	}
+}
+
 #[derive(Debug, Clone)]
 pub enum TokenLiteral {
 	String(String),
-	Integer(i32),
-	Float(f32),
+	Integer(i64),
+	Float(f64),
 	Bool(bool),
 	Nil(()),
 }
@@ -70,7 +143,7 @@ pub struct Token {
 	pub token_type: TokenType,
 	pub lexeme: String,
 	pub literal: Option<TokenLiteral>,
-	pub line: i32,
+	pub span: Span,
 }
 
 impl std::cmp::PartialEq for Token {
@@ -79,28 +152,181 @@ impl std::cmp::PartialEq for Token {
 	}
 }
 
+/// Where a character is in the source.
+#[derive(Clone, Copy)]
+pub struct Position {
+	offset: usize,
+	line: i32,
+	column: i32,
+}
+
+/// The characters of the source being tokenized, along with the position
+/// reached in it.
+pub struct Cursor<'a> {
+	chars: Peekable<Chars<'a>>,
+	file: Rc<str>,
+	offset: usize,
+	line: i32,
+	/// The number of characters taken from the current line.
+	column: i32,
+}
+
+impl<'a> Cursor<'a> {
+	fn new(source: &'a str, file: &str) -> Self {
+		Self {
+			chars: source.chars().peekable(),
+			file: Rc::from(file),
+			offset: 0,
+			line: 1,
+			column: 0,
+		}
+	}
+
+	/// The position of the next character.
+	fn location(&self) -> Position {
+		Position {
+			offset: self.offset,
+			line: self.line,
+			column: self.column + 1,
+		}
+	}
+
+	/// The span from `start` up to the next character.
+	fn span(&self, start: Position) -> Span {
+		Span {
+			file: self.file.clone(),
+			start: start.offset,
+			end: self.offset,
+			line: start.line,
+			column: start.column,
+			end_line: self.line,
+			end_column: self.column + 1,
+		}
+	}
+
+	fn peek(&mut self) -> Option<&char> {
+		self.chars.peek()
+	}
+
+	/// Looks at the character after the next one.
+	fn peek_second(&self) -> Option<char> {
+		self.chars.clone().nth(1)
+	}
+
+	/// Takes the next character if it is `expected`.
+	fn next_if_eq(&mut self, expected: char) -> Option<char> {
+		self.next_if(|c| *c == expected)
+	}
+
+	/// Takes the next character if it satisfies `predicate`.
+	fn next_if(&mut self, predicate: impl FnOnce(&char) -> bool) -> Option<char> {
+		match self.chars.peek() {
+			Some(c) if predicate(c) => self.next(),
+			_ => None,
+		}
+	}
+}
+
+impl Iterator for Cursor<'_> {
+	type Item = char;
+
+	fn next(&mut self) -> Option<char> {
+		let c = self.chars.next()?;
+		self.offset += c.len_utf8();
+		match c {
+			'\n' => {
+				self.line += 1;
+				self.column = 0;
+			}
+			_ => self.column += 1,
+		}
+		Some(c)
+	}
+}
+
+/// How the body of a string literal is delimited and decoded.
+#[derive(Clone, Copy, PartialEq)]
+enum Quoting {
+	/// `"..."`, with escape sequences and `${...}` interpolation.
+	Plain,
+	/// `"""..."""`, with escape sequences and the common indentation of its
+	/// lines removed.
+	Triple,
+	/// `r"..."`, or `r#"..."#` with any number of `#`s, without escape
+	/// sequences.
+	Raw(usize),
+}
+
+/// An interpolated expression being lexed inside a string literal.
+pub struct OpenInterpolation {
+	/// Where the string literal starts.
+	start: Position,
+	/// How many braces opened within the expression are still open.
+	depth: usize,
+}
+
 impl Token {
-	pub fn tokenize(source: String) -> Vec<Result<Self, Error>> {
-		let mut tokens: Vec<Result<Token, Error>> = Vec::new();
-		let mut stream = source.chars().peekable();
-		let mut line = 1;
+	/// Splits `source`, read from `file`, into tokens, along with the errors
+	/// found in it.
+	///
+	/// Source that fails to tokenize becomes an `Invalid` token, and
+	/// tokenizing carries on after it.
+	pub fn tokenize(source: String, file: &str) -> (Vec<Self>, Vec<Error>) {
+		let mut tokens: Vec<Token> = Vec::new();
+		let mut errors = Vec::new();
+		let mut stream = Cursor::new(&source, file);
+		let mut interpolations = Vec::new();
 
 		loop {
-			if stream.clone().count() <= 0 {
-				tokens.push(Self::try_parse(&mut stream, &mut line));
+			let at_end = stream.peek().is_none();
+			if at_end {
+				// Only the outermost string is reported, as the strings
+				// inside it end with it.
+				if let Some(OpenInterpolation { start, .. }) = interpolations.first() {
+					errors.push(Error::UnterminatedString(stream.span(*start)));
+				}
+				for _ in interpolations.iter() {
+					tokens.push(Token {
+						token_type: TokenType::Invalid,
+						lexeme: std::string::String::default(),
+						literal: None,
+						span: stream.span(stream.location()),
+					});
+				}
+			}
+			let start = stream.location();
+			match Self::try_parse(&mut stream, &mut interpolations) {
+				Ok(token) => tokens.push(token),
+				Err(e) => {
+					// A string running to the end inside an interpolated
+					// expression leaves the string around it unterminated too,
+					// which is reported instead.
+					let enclosed = matches!(e, Error::UnterminatedString(_)) && !interpolations.is_empty();
+					if !enclosed {
+						errors.push(e);
+					}
+					tokens.push(Token {
+						token_type: TokenType::Invalid,
+						lexeme: source[start.offset..stream.offset].to_string(),
+						literal: None,
+						span: stream.span(start),
+					});
+				}
+			}
+			if at_end {
 				break;
 			}
-			tokens.push(Self::try_parse(&mut stream, &mut line));
 		}
 
-		tokens
+		(tokens, errors)
 	}
 
-	fn try_parse<'a>(
-		source: &mut Peekable<Chars<'a>>,
-		line: &mut i32,
+	fn try_parse(
+		source: &mut Cursor,
+		interpolations: &mut Vec<OpenInterpolation>,
 	) -> Result<Self, Error> {
 		use TokenType::*;
+		let start = source.location();
 		let c = match source.next() {
 			Some(v) => v,
 			_ => {
@@ -108,7 +334,7 @@ impl Token {
 					token_type: TokenType::Eof,
 					lexeme: '\0'.to_string(),
 					literal: None,
-					line: *line,
+					span: source.span(start),
 				})
 			}
 		};
@@ -118,61 +344,189 @@ impl Token {
 				token_type: LeftParen,
 				lexeme: c.to_string(),
 				literal: None,
-				line: *line,
+				span: source.span(start),
 			}),
 			')' => Ok(Token {
 				token_type: RightParen,
 				lexeme: c.to_string(),
 				literal: None,
-				line: *line,
+				span: source.span(start),
 			}),
-			'{' => Ok(Token {
-				token_type: LeftBrace,
+			'{' => {
+				if let Some(interpolation) = interpolations.last_mut() {
+					interpolation.depth += 1;
+				}
+				Ok(Token {
+					token_type: LeftBrace,
+					lexeme: c.to_string(),
+					literal: None,
+					span: source.span(start),
+				})
+			}
+			// Closes the interpolated expression, and lexes the rest of the
+			// string it is in.
+			'}' if matches!(interpolations.last(), Some(OpenInterpolation { depth: 0, .. })) => {
+				let interpolation = interpolations.pop().unwrap();
+				let mut token = Self::interpolated(source, start, c.to_string(), interpolation.start, interpolations)?;
+				token.token_type = match token.token_type {
+					Interpolation => InterpolationMiddle,
+					_ => InterpolationEnd,
+				};
+				Ok(token)
+			}
+			'}' => {
+				if let Some(interpolation) = interpolations.last_mut() {
+					interpolation.depth -= 1;
+				}
+				Ok(Token {
+					token_type: RightBrace,
+					lexeme: c.to_string(),
+					literal: None,
+					span: source.span(start),
+				})
+			}
+			'[' => Ok(Token {
+				token_type: LeftBracket,
 				lexeme: c.to_string(),
 				literal: None,
-				line: *line,
+				span: source.span(start),
 			}),
-			'}' => Ok(Token {
-				token_type: RightBrace,
+			']' => Ok(Token {
+				token_type: RightBracket,
 				lexeme: c.to_string(),
 				literal: None,
-				line: *line,
+				span: source.span(start),
 			}),
 			'.' => Ok(Token {
 				token_type: Dot,
 				lexeme: c.to_string(),
 				literal: None,
-				line: *line,
+				span: source.span(start),
+			}),
+			':' => Ok(Token {
+				token_type: Colon,
+				lexeme: c.to_string(),
+				literal: None,
+				span: source.span(start),
 			}),
 			',' => Ok(Token {
 				token_type: Comma,
 				lexeme: c.to_string(),
 				literal: None,
-				line: *line,
+				span: source.span(start),
 			}),
-			'-' => Ok(Token {
-				token_type: Minus,
+			'-' => match source.peek() {
+				Some('=') => {
+					let nc = source.next().unwrap();
+					Ok(Token {
+						token_type: MinusEqual,
+						lexeme: format!("{c}{nc}").to_string(),
+						literal: None,
+						span: source.span(start),
+					})
+				}
+				_ => Ok(Token {
+					token_type: Minus,
+					lexeme: c.to_string(),
+					literal: None,
+					span: source.span(start),
+				}),
+			},
+			'+' => match source.peek() {
+				Some('=') => {
+					let nc = source.next().unwrap();
+					Ok(Token {
+						token_type: PlusEqual,
+						lexeme: format!("{c}{nc}").to_string(),
+						literal: None,
+						span: source.span(start),
+					})
+				}
+				_ => Ok(Token {
+					token_type: Plus,
+					lexeme: c.to_string(),
+					literal: None,
+					span: source.span(start),
+				}),
+			},
+			';' => Ok(Token {
+				token_type: SemiColon,
 				lexeme: c.to_string(),
 				literal: None,
-				line: *line,
+				span: source.span(start),
 			}),
-			'+' => Ok(Token {
-				token_type: Plus,
+			'*' => match source.peek() {
+				Some('*') => {
+					let nc = source.next().unwrap();
+					Ok(Token {
+						token_type: StarStar,
+						lexeme: format!("{c}{nc}").to_string(),
+						literal: None,
+						span: source.span(start),
+					})
+				}
+				Some('=') => {
+					let nc = source.next().unwrap();
+					Ok(Token {
+						token_type: StarEqual,
+						lexeme: format!("{c}{nc}").to_string(),
+						literal: None,
+						span: source.span(start),
+					})
+				}
+				_ => Ok(Token {
+					token_type: Star,
+					lexeme: c.to_string(),
+					literal: None,
+					span: source.span(start),
+				}),
+			},
+			'%' => match source.peek() {
+				Some('=') => {
+					let nc = source.next().unwrap();
+					Ok(Token {
+						token_type: PercentEqual,
+						lexeme: format!("{c}{nc}").to_string(),
+						literal: None,
+						span: source.span(start),
+					})
+				}
+				_ => Ok(Token {
+					token_type: Percent,
+					lexeme: c.to_string(),
+					literal: None,
+					span: source.span(start),
+				}),
+			},
+			'?' => Ok(Token {
+				token_type: Question,
 				lexeme: c.to_string(),
 				literal: None,
-				line: *line,
+				span: source.span(start),
 			}),
-			';' => Ok(Token {
-				token_type: SemiColon,
+			'&' => Ok(Token {
+				token_type: Ampersand,
+				lexeme: c.to_string(),
+				literal: None,
+				span: source.span(start),
+			}),
+			'|' => Ok(Token {
+				token_type: Pipe,
 				lexeme: c.to_string(),
 				literal: None,
-				line: *line,
+				span: source.span(start),
 			}),
-			'*' => Ok(Token {
-				token_type: Star,
+			'^' => Ok(Token {
+				token_type: Caret,
 				lexeme: c.to_string(),
 				literal: None,
-				line: *line,
+				span: source.span(start),
+			}),
+			'~' => Ok(Token {
+				token_type: Tilde,
+				lexeme: c.to_string(),
+				literal: None,
+				span: source.span(start),
 			}),
 			'!' => match source.peek() {
 				Some('=') => {
@@ -181,14 +535,14 @@ impl Token {
 						token_type: BangEqual,
 						lexeme: format!("{c}{nc}").to_string(),
 						literal: None,
-						line: *line,
+						span: source.span(start),
 					})
 				}
 				_ => Ok(Token {
 					token_type: Bang,
 					lexeme: c.to_string(),
 					literal: None,
-					line: *line,
+					span: source.span(start),
 				}),
 			},
 			'=' => match source.peek() {
@@ -198,212 +552,188 @@ impl Token {
 						token_type: EqualEqual,
 						lexeme: format!("{c}{nc}").to_string(),
 						literal: None,
-						line: *line,
+						span: source.span(start),
 					})
 				}
 				_ => Ok(Token {
 					token_type: Equal,
 					lexeme: c.to_string(),
 					literal: None,
-					line: *line,
+					span: source.span(start),
 				}),
 			},
 			'<' => match source.peek() {
+				Some('<') => {
+					let nc = source.next().unwrap();
+					Ok(Token {
+						token_type: LessLess,
+						lexeme: format!("{c}{nc}").to_string(),
+						literal: None,
+						span: source.span(start),
+					})
+				}
 				Some('=') => {
 					let nc = source.next().unwrap();
 					Ok(Token {
 						token_type: LessEqual,
 						lexeme: format!("{c}{nc}").to_string(),
 						literal: None,
-						line: *line,
+						span: source.span(start),
 					})
 				}
 				_ => Ok(Token {
 					token_type: Less,
 					lexeme: c.to_string(),
 					literal: None,
-					line: *line,
+					span: source.span(start),
 				}),
 			},
 			'>' => match source.peek() {
+				Some('>') => {
+					let nc = source.next().unwrap();
+					Ok(Token {
+						token_type: GreaterGreater,
+						lexeme: format!("{c}{nc}").to_string(),
+						literal: None,
+						span: source.span(start),
+					})
+				}
 				Some('=') => {
 					let nc = source.next().unwrap();
 					Ok(Token {
 						token_type: GreaterEqual,
 						lexeme: format!("{c}{nc}").to_string(),
 						literal: None,
-						line: *line,
+						span: source.span(start),
 					})
 				}
 				_ => Ok(Token {
 					token_type: Greater,
 					lexeme: c.to_string(),
 					literal: None,
-					line: *line,
+					span: source.span(start),
 				}),
 			},
 			'/' => match source.peek() {
+				Some('=') => {
+					let nc = source.next().unwrap();
+					Ok(Token {
+						token_type: SlashEqual,
+						lexeme: format!("{c}{nc}").to_string(),
+						literal: None,
+						span: source.span(start),
+					})
+				}
 				Some('/') => {
 					let mut comment = std::string::String::from(c);
-					loop {
-						match source.take_while(|sc| *sc != '\n').next() {
-							Some(nc) => comment.push(nc),
-							_ => {
-								*line += 1;
-								return Ok(Token {
-									token_type: SingleLineComment,
-									lexeme: comment,
-									literal: None,
-									line: *line - 1,
-								});
+					// The newline is left to be lexed as whitespace.
+					while let Some(nc) = source.next_if(|nc| *nc != '\n') {
+						comment.push(nc);
+					}
+					// `////...` is a plain comment, such as a divider.
+					let token_type = match comment.starts_with("///") && !comment.starts_with("////") {
+						true => DocComment,
+						false => SingleLineComment,
+					};
+					Ok(Token {
+						token_type,
+						lexeme: comment,
+						literal: None,
+						span: source.span(start),
+					})
+				}
+				Some('*') => {
+					let mut comment = std::string::String::from(c);
+					comment.push(source.next().unwrap());
+					let mut depth = 1;
+					while depth > 0 {
+						let Some(nc) = source.next() else {
+							return Err(Error::UnterminatedComment(source.span(start)));
+						};
+						comment.push(nc);
+						match nc {
+							'/' if source.peek() == Some(&'*') => {
+								comment.push(source.next().unwrap());
+								depth += 1;
+							}
+							'*' if source.peek() == Some(&'/') => {
+								comment.push(source.next().unwrap());
+								depth -= 1;
 							}
+							_ => {}
 						}
 					}
+					Ok(Token {
+						token_type: BlockComment,
+						lexeme: comment,
+						literal: None,
+						span: source.span(start),
+					})
 				}
 				_ => Ok(Token {
 					token_type: Slash,
 					lexeme: c.to_string(),
 					literal: None,
-					line: *line,
+					span: source.span(start),
 				}),
 			},
-			' ' | '\r' | '\t' => Ok(Token {
+			' ' | '\r' | '\t' | '\n' => Ok(Token {
 				token_type: Whitespace,
 				lexeme: c.to_string(),
 				literal: None,
-				line: *line,
+				span: source.span(start),
 			}),
-			'\n' => {
-				*line += 1;
-				Ok(Token {
-					token_type: Whitespace,
-					lexeme: c.to_string(),
-					literal: None,
-					line: *line,
-				})
-			}
 			'"' => {
-				let mut literal = std::string::String::default();
-				loop {
-					let c = source.next();
-					match c {
-						Some('"') => {
-							return Ok(Token {
-								token_type: TokenType::String,
-								lexeme: format!("\"{literal}\""),
-								literal: Some(TokenLiteral::String(literal)),
-								line: *line,
-							})
-						}
-						Some('\n') => {
-							*line += 1;
-							literal.push('\n');
+				let mut lexeme = std::string::String::from(c);
+				let quoting = match source.next_if_eq('"') {
+					Some(c) => {
+						lexeme.push(c);
+						match source.next_if_eq('"') {
+							Some(c) => {
+								lexeme.push(c);
+								Quoting::Triple
+							}
+							// `""` is an empty string.
+							None => {
+								return Ok(Token {
+									token_type: TokenType::String,
+									lexeme,
+									literal: Some(TokenLiteral::String(std::string::String::default())),
+									span: source.span(start),
+								})
+							}
 						}
-						Some(c) => literal.push(c),
-						_ => return Err(Error::UnterminatedString(*line)),
 					}
+					None => Quoting::Plain,
+				};
+				match quoting {
+					Quoting::Plain => Self::interpolated(source, start, lexeme, start, interpolations),
+					_ => Self::string(source, start, lexeme, quoting),
 				}
 			}
-			'0'..='9' => {
-				let mut literal = std::string::String::from(c);
-				let mut is_decimal = false;
-				loop {
-					let c = source.peek();
-					match c {
-						Some(c) => match c {
-							'0'..='9' => literal.push(source.next().unwrap()),
-							'.' => {
-								if is_decimal {
-									return Err(Error::UnparsableNumber(
-										*line,
-										"invalid float literal".to_string(),
-									));
-								}
-								is_decimal = true;
-								literal.push(source.next().unwrap());
-							}
-							_ => {
-								if is_decimal {
-									return literal
-										.parse::<f32>()
-										.map_err(|e| {
-											Error::UnparsableNumber(
-												*line,
-												e.to_string(),
-											)
-										})
-										.map(|float| Token {
-											token_type: Float,
-											lexeme: literal,
-											literal: Some(TokenLiteral::Float(float)),
-											line: *line,
-										});
-								}
-								return literal
-									.parse::<i32>()
-									.map_err(|e| {
-										Error::UnparsableNumber(*line, e.to_string())
-									})
-									.map(|int| Token {
-										token_type: Integer,
-										lexeme: literal,
-										literal: Some(TokenLiteral::Integer(int)),
-										line: *line,
-									});
-							}
-						},
-						_ => {
-							if is_decimal {
-								return literal
-									.parse::<f32>()
-									.map_err(|e| {
-										Error::UnparsableNumber(*line, e.to_string())
-									})
-									.map(|float| Token {
-										token_type: Float,
-										lexeme: literal,
-										literal: Some(TokenLiteral::Float(float)),
-										line: *line,
-									});
-							}
-							return literal
-								.parse::<i32>()
-								.map_err(|e| {
-									Error::UnparsableNumber(*line, e.to_string())
-								})
-								.map(|int| Token {
-									token_type: Integer,
-									lexeme: literal,
-									literal: Some(TokenLiteral::Integer(int)),
-									line: *line,
-								});
-						}
+			'r' if matches!(source.peek(), Some('"') | Some('#')) => {
+				let mut lexeme = std::string::String::from(c);
+				let mut hashes = 0;
+				while let Some(c) = source.next_if_eq('#') {
+					lexeme.push(c);
+					hashes += 1;
+				}
+				match source.next_if_eq('"') {
+					Some(c) => {
+						lexeme.push(c);
+						Self::string(source, start, lexeme, Quoting::Raw(hashes))
 					}
+					None => Err(Error::UnexpectedToken(source.span(start), lexeme)),
 				}
 			}
+			'0'..='9' => Self::number(source, start, c),
 			'a'..='z' | 'A'..='Z' | '_' => {
 				let mut literal = std::string::String::from(c);
 				let keyword = |lit: std::string::String| -> TokenType {
-					match lit.as_str() {
-						"and" => And,
-						"class" => Class,
-						"else" => Else,
-						"false" => False,
-						"for" => For,
-						"fun" => Fun,
-						"fn" => Fn,
-						"if" => If,
-						"nil" => Nil,
-						"or" => Or,
-						"print" => Print,
-						"return" => Return,
-						"super" => Super,
-						"this" => This,
-						"true" => True,
-						"var" => Var,
-						"while" => While,
-						_ => Identifier,
-					}
+					KEYWORDS
+						.iter()
+						.find(|(keyword, _)| *keyword == lit)
+						.map_or(Identifier, |(_, token_type)| token_type.clone())
 				};
 				loop {
 					let c = source.peek();
@@ -421,7 +751,7 @@ impl Token {
 										literal: Some(TokenLiteral::Bool(
 											literal.as_str() == "true",
 										)),
-										line: *line,
+										span: source.span(start),
 									});
 								}
 								if ttype == Nil {
@@ -429,14 +759,14 @@ impl Token {
 										token_type: ttype,
 										lexeme: literal.clone(),
 										literal: Some(TokenLiteral::Nil(())),
-										line: *line,
+										span: source.span(start),
 									});
 								}
 								return Ok(Token {
 									token_type: ttype,
 									lexeme: literal,
 									literal: None,
-									line: *line,
+									span: source.span(start),
 								});
 							}
 						},
@@ -449,20 +779,332 @@ impl Token {
 									literal: Some(TokenLiteral::Bool(
 										literal.as_str() == "true",
 									)),
-									line: *line,
+									span: source.span(start),
 								});
 							}
 							return Ok(Token {
 								token_type: ttype,
 								lexeme: literal,
 								literal: None,
-								line: *line,
+								span: source.span(start),
 							});
 						}
 					}
 				}
 			}
-			_ => Err(Error::UnexpectedToken(*line, c.to_string())),
+			_ => Err(Error::UnexpectedToken(source.span(start), c.to_string())),
 		}
 	}
+
+	/// Lexes a number literal starting with the digit `first`: an int in
+	/// decimal, or in hexadecimal, octal or binary after a `0x`, `0o` or `0b`
+	/// prefix, or a decimal float with a fraction, an exponent or both.
+	/// Digits may be separated by single underscores.
+	fn number(source: &mut Cursor, start: Position, first: char) -> Result<Self, Error> {
+		let mut lexeme = std::string::String::from(first);
+		let invalid = |source: &Cursor, lexeme: &str, reason: &dyn Display| {
+			Error::UnparsableNumber(source.span(start), format!("Invalid number literal {lexeme}: {reason}"))
+		};
+		let take_digits = |source: &mut Cursor, lexeme: &mut std::string::String| {
+			while let Some(c) = source.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+				lexeme.push(c);
+			}
+		};
+		// Separators must sit between two digits.
+		let separated = |digits: &str, radix: u32| {
+			let chars = digits.chars().collect::<Vec<char>>();
+			chars.iter().enumerate().all(|(i, c)| {
+				*c != '_'
+					|| (i > 0
+						&& i + 1 < chars.len() && chars[i - 1].is_digit(radix)
+						&& chars[i + 1].is_digit(radix))
+			})
+		};
+
+		let radix = match (first, source.peek()) {
+			('0', Some('x' | 'X')) => Some(16),
+			('0', Some('o' | 'O')) => Some(8),
+			('0', Some('b' | 'B')) => Some(2),
+			_ => None,
+		};
+		if let Some(radix) = radix {
+			lexeme.push(source.next().unwrap());
+			take_digits(source, &mut lexeme);
+			let digits = &lexeme[2..];
+			if digits.is_empty() {
+				return Err(invalid(source, &lexeme, &"missing digits"));
+			}
+			if !separated(digits, radix) {
+				return Err(invalid(source, &lexeme, &"misplaced digit separator"));
+			}
+			return i64::from_str_radix(&digits.replace('_', ""), radix)
+				.map_err(|e| invalid(source, &lexeme, &e))
+				.map(|int| Token {
+					token_type: TokenType::Integer,
+					lexeme,
+					literal: Some(TokenLiteral::Integer(int)),
+					span: source.span(start),
+				});
+		}
+
+		let mut is_float = false;
+		while let Some(c) = source.next_if(|c| c.is_ascii_digit() || *c == '_') {
+			lexeme.push(c);
+		}
+		// A dot not followed by a digit is left alone, as in `1.method`.
+		if source.peek() == Some(&'.') && source.peek_second().is_some_and(|c| c.is_ascii_digit()) {
+			is_float = true;
+			lexeme.push(source.next().unwrap());
+			while let Some(c) = source.next_if(|c| c.is_ascii_digit() || *c == '_') {
+				lexeme.push(c);
+			}
+		}
+		if let Some(e) = source.next_if(|c| *c == 'e' || *c == 'E') {
+			is_float = true;
+			lexeme.push(e);
+			if let Some(sign) = source.next_if(|c| *c == '+' || *c == '-') {
+				lexeme.push(sign);
+			}
+			if !source.peek().is_some_and(|c| c.is_ascii_digit()) {
+				take_digits(source, &mut lexeme);
+				return Err(invalid(source, &lexeme, &"missing exponent digits"));
+			}
+			while let Some(c) = source.next_if(|c| c.is_ascii_digit() || *c == '_') {
+				lexeme.push(c);
+			}
+		}
+		let end = lexeme.len();
+		take_digits(source, &mut lexeme);
+		if lexeme.len() > end {
+			return Err(invalid(source, &lexeme, &format!("invalid suffix {}", &lexeme[end..])));
+		}
+		let parts_separated = lexeme
+			.split(['.', 'e', 'E', '+', '-'])
+			.all(|part| separated(part, 10));
+		if !parts_separated {
+			return Err(invalid(source, &lexeme, &"misplaced digit separator"));
+		}
+
+		let digits = lexeme.replace('_', "");
+		if !is_float {
+			return digits
+				.parse::<i64>()
+				.map_err(|e| invalid(source, &lexeme, &e))
+				.map(|int| Token {
+					token_type: TokenType::Integer,
+					lexeme,
+					literal: Some(TokenLiteral::Integer(int)),
+					span: source.span(start),
+				});
+		}
+		match digits.parse::<f64>() {
+			Ok(float) if float.is_infinite() => Err(invalid(source, &lexeme, &"number too large to fit in a float")),
+			Ok(float) => Ok(Token {
+				token_type: TokenType::Float,
+				lexeme,
+				literal: Some(TokenLiteral::Float(float)),
+				span: source.span(start),
+			}),
+			Err(e) => Err(invalid(source, &lexeme, &e)),
+		}
+	}
+
+	/// Lexes a plain string literal, or the part of one following an
+	/// interpolated expression, where the string began at `string_start`.
+	fn interpolated(
+		source: &mut Cursor,
+		start: Position,
+		lexeme: std::string::String,
+		string_start: Position,
+		interpolations: &mut Vec<OpenInterpolation>,
+	) -> Result<Self, Error> {
+		// An unterminated string is reported where it began.
+		let token = Self::string(source, start, lexeme, Quoting::Plain).map_err(|e| match e {
+			Error::UnterminatedString(_) => Error::UnterminatedString(source.span(string_start)),
+			e => e,
+		})?;
+		if token.token_type == TokenType::Interpolation {
+			interpolations.push(OpenInterpolation {
+				start: string_start,
+				depth: 0,
+			});
+		}
+		Ok(token)
+	}
+
+	/// Lexes the rest of a string literal whose opening delimiter, already
+	/// taken from `source`, begins `lexeme`.
+	///
+	/// Plain strings stop early at a `${`, giving an `Interpolation` token.
+	///
+	/// An invalid escape sequence does not stop the string from being read
+	/// to its end, so that lexing can carry on after it.
+	fn string(
+		source: &mut Cursor,
+		start: Position,
+		mut lexeme: std::string::String,
+		quoting: Quoting,
+	) -> Result<Self, Error> {
+		// Each character of the value, and whether it came from an escape
+		// sequence rather than appearing literally in the source.
+		let mut chars: Vec<(char, bool)> = Vec::new();
+		let mut error = None;
+		let mut token_type = TokenType::String;
+		loop {
+			let position = source.location();
+			let Some(c) = source.next() else {
+				return Err(Error::UnterminatedString(source.span(start)));
+			};
+			lexeme.push(c);
+			match (c, quoting) {
+				('"', Quoting::Plain) => break,
+				('$', Quoting::Plain) if source.peek() == Some(&'{') => {
+					lexeme.push(source.next().unwrap());
+					token_type = TokenType::Interpolation;
+					break;
+				}
+				('"', Quoting::Triple) => {
+					let mut quotes = 1;
+					while quotes < 3 {
+						match source.next_if_eq('"') {
+							Some(c) => lexeme.push(c),
+							None => break,
+						}
+						quotes += 1;
+					}
+					if quotes == 3 {
+						break;
+					}
+					chars.extend(std::iter::repeat_n(('"', false), quotes));
+				}
+				('"', Quoting::Raw(hashes)) => {
+					let mut found = 0;
+					while found < hashes {
+						match source.next_if_eq('#') {
+							Some(c) => lexeme.push(c),
+							None => break,
+						}
+						found += 1;
+					}
+					if found == hashes {
+						break;
+					}
+					chars.push(('"', false));
+					chars.extend(std::iter::repeat_n(('#', false), found));
+				}
+				('\\', Quoting::Plain | Quoting::Triple) => {
+					match Self::escape(source, position, &mut lexeme) {
+						Ok(c) => chars.push((c, true)),
+						Err(e) => {
+							error.get_or_insert(e);
+						}
+					}
+				}
+				_ => chars.push((c, false)),
+			}
+		}
+
+		if let Some(error) = error {
+			return Err(error);
+		}
+		let literal = match quoting {
+			Quoting::Triple => Self::strip_indentation(&chars),
+			_ => chars.into_iter().map(|(c, _)| c).collect(),
+		};
+		Ok(Token {
+			token_type,
+			lexeme,
+			literal: Some(TokenLiteral::String(literal)),
+			span: source.span(start),
+		})
+	}
+
+	/// Decodes the escape sequence whose backslash, found at `backslash`,
+	/// was just taken from `source`, adding its source to `lexeme`.
+	fn escape(source: &mut Cursor, backslash: Position, lexeme: &mut std::string::String) -> Result<char, Error> {
+		let start = lexeme.len() - 1;
+		let invalid = |source: &Cursor, lexeme: &str| Error::InvalidEscape(source.span(backslash), lexeme[start..].to_string());
+		let Some(c) = source.peek().copied() else {
+			return Err(Error::UnterminatedString(source.span(backslash)));
+		};
+		// A newline is left for the caller, so that it ends up in the value.
+		if c == '\n' {
+			return Err(invalid(source, lexeme));
+		}
+		lexeme.push(source.next().unwrap());
+		match c {
+			'n' => Ok('\n'),
+			't' => Ok('\t'),
+			'r' => Ok('\r'),
+			'0' => Ok('\0'),
+			'\\' => Ok('\\'),
+			'"' => Ok('"'),
+			'$' => Ok('$'),
+			'\'' => Ok('\''),
+			'u' => {
+				match source.next_if_eq('{') {
+					Some(c) => lexeme.push(c),
+					None => return Err(invalid(source, lexeme)),
+				}
+				let mut digits = std::string::String::default();
+				loop {
+					match source.peek() {
+						Some('}') => {
+							lexeme.push(source.next().unwrap());
+							break;
+						}
+						Some(c) if c.is_ascii_hexdigit() && digits.len() < 6 => {
+							digits.push(*c);
+							lexeme.push(source.next().unwrap());
+						}
+						_ => return Err(invalid(source, lexeme)),
+					}
+				}
+				u32::from_str_radix(&digits, 16)
+					.ok()
+					.and_then(char::from_u32)
+					.ok_or_else(|| invalid(source, lexeme))
+			}
+			_ => Err(invalid(source, lexeme)),
+		}
+	}
+
+	/// Builds the value of a triple-quoted string from its characters.
+	///
+	/// A line break straight after the opening quotes is dropped, as is the
+	/// last line when it holds nothing but the indentation of the closing
+	/// quotes. The indentation shared by the remaining non-blank lines and
+	/// the closing quotes is then removed from every line. Only whitespace
+	/// appearing literally in the source counts as indentation.
+	fn strip_indentation(chars: &[(char, bool)]) -> std::string::String {
+		let mut lines: Vec<&[(char, bool)]> = chars.split(|c| *c == ('\n', false)).collect();
+		let is_blank = |line: &[(char, bool)]| line.iter().all(|c| *c == (' ', false) || *c == ('\t', false));
+		let indentation = |line: &[(char, bool)]| line.iter().take_while(|c| **c == (' ', false) || **c == ('\t', false)).count();
+
+		if lines.len() > 1 && is_blank(lines[0]) {
+			lines.remove(0);
+		}
+		let closing = match lines.last() {
+			Some(last) if lines.len() > 1 && is_blank(last) => lines.pop().map(|last| last.len()),
+			_ => None,
+		};
+		let common = lines
+			.iter()
+			.filter(|line| !is_blank(line))
+			.map(|line| indentation(line))
+			.chain(closing)
+			.min()
+			.unwrap_or_default();
+
+		lines
+			.iter()
+			.map(|line| {
+				line[common.min(indentation(line))..]
+					.iter()
+					.map(|(c, _)| c)
+					.collect::<std::string::String>()
+			})
+			.collect::<Vec<_>>()
+			.join("\n")
+	}
 }
@@ -1,22 +1,68 @@
+use std::cell::RefCell;
 use std::cmp::Ordering;
+use std::rc::Rc;
 
+use crate::interpreter::callable::{Callable, LoxFunction};
+use crate::interpreter::class::{LoxClass, LoxInstance};
+use crate::interpreter::map::LoxMap;
+use crate::interpreter::native::NativeFunction;
 use crate::interpreter::token::*;
 use crate::prelude::*;
 
 #[derive(Debug, Clone)]
 pub enum LoxValue {
     Nil,
-    Number(f64),
+    Int(i64),
+    Float(f64),
     Bool(bool),
     String(String),
+    Function(Rc<LoxFunction>),
+    NativeFunction(Rc<NativeFunction>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+    List(Rc<RefCell<Vec<LoxValue>>>),
+    Map(Rc<RefCell<LoxMap>>),
+}
+
+/// The rules for how values of other types stand in for booleans, in
+/// conditions, `!`, `and` and `or`, and for comparing values of different
+/// types with `==` and `!=`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Dialect {
+    /// Only bools can be used as conditions or logical operands, and
+    /// comparing values of different types is an error, except with `nil`.
+    #[default]
+    Strict,
+    /// The semantics of canonical Lox: `nil` and `false` are falsy and every
+    /// other value truthy, and values of different types are never equal.
+    Canonical,
+}
+
+impl Dialect {
+    /// Whether `value` counts as true, or `None` if it cannot be used as a
+    /// boolean.
+    pub fn truthiness(&self, value: &LoxValue) -> Option<bool> {
+        match (self, value) {
+            (_, LoxValue::Bool(b)) => Some(*b),
+            (Self::Strict, _) => None,
+            (Self::Canonical, LoxValue::Nil) => Some(false),
+            (Self::Canonical, _) => Some(true),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum LoxType {
     Nil = 0,
-    Number,
+    Int,
+    Float,
     Bool,
     String,
+    Function,
+    Class,
+    Instance,
+    List,
+    Map,
 }
 
 impl From<LoxType> for String {
@@ -24,9 +70,15 @@ impl From<LoxType> for String {
         use LoxType::*;
         match value {
             Nil => "nil".to_string(),
-            Number => "number".to_string(),
+            Int => "int".to_string(),
+            Float => "float".to_string(),
             Bool => "bool".to_string(),
             String => "string".to_string(),
+            Function => "function".to_string(),
+            Class => "class".to_string(),
+            Instance => "instance".to_string(),
+            List => "list".to_string(),
+            Map => "map".to_string(),
         }
     }
 }
@@ -34,9 +86,15 @@ impl From<LoxType> for String {
 impl LoxValue {
     pub fn lox_type(&self) -> LoxType {
         match self {
-            Self::Number(_) => LoxType::Number,
+            Self::Int(_) => LoxType::Int,
+            Self::Float(_) => LoxType::Float,
             Self::Bool(_) => LoxType::Bool,
             Self::String(_) => LoxType::String,
+            Self::Function(_) | Self::NativeFunction(_) => LoxType::Function,
+            Self::Class(_) => LoxType::Class,
+            Self::Instance(_) => LoxType::Instance,
+            Self::List(_) => LoxType::List,
+            Self::Map(_) => LoxType::Map,
             Self::Nil => LoxType::Nil,
         }
     }
@@ -45,10 +103,29 @@ impl LoxValue {
         self.lox_type() == other.lox_type()
     }
 
+    pub fn is_number(&self) -> bool {
+        matches!(self, Self::Int(_) | Self::Float(_))
+    }
+
+    /// Whether the value can be compared with `other`: either both are of
+    /// the same type, or both are numbers.
+    pub fn is_comparable_with(&self, other: &Self) -> bool {
+        self.is_same_type_as(other) || (self.is_number() && other.is_number())
+    }
+
     pub fn is_nil(&self) -> bool {
         self.lox_type() == LoxType::Nil
     }
 
+	pub(crate) fn as_callable(&self) -> Option<&dyn Callable> {
+		match self {
+			Self::Function(f) => Some(f.as_ref()),
+			Self::NativeFunction(f) => Some(f.as_ref()),
+			Self::Class(c) => Some(c),
+			_ => None,
+		}
+	}
+
 	pub fn into_bool(self) -> Option<bool> {
 		match self {
 			Self::Bool(b) => Some(b),
@@ -60,9 +137,17 @@ impl LoxValue {
 impl PartialEq for LoxValue {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
-            (Self::Number(lhs), Self::Number(rhs)) => lhs == rhs,
+            (Self::Int(lhs), Self::Int(rhs)) => lhs == rhs,
+            (Self::Float(lhs), Self::Float(rhs)) => lhs == rhs,
+            (Self::Int(i), Self::Float(f)) | (Self::Float(f), Self::Int(i)) => float_to_int(*f) == Some(*i),
             (Self::String(lhs), Self::String(rhs)) => lhs == rhs,
             (Self::Bool(lhs), Self::Bool(rhs)) => lhs == rhs,
+            (Self::Function(lhs), Self::Function(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Self::NativeFunction(lhs), Self::NativeFunction(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Self::Class(lhs), Self::Class(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Self::Instance(lhs), Self::Instance(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Self::List(lhs), Self::List(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Self::Map(lhs), Self::Map(rhs)) => Rc::ptr_eq(lhs, rhs),
             (Self::Nil, Self::Nil) => true,
             _ => false,
         }
@@ -72,19 +157,78 @@ impl PartialEq for LoxValue {
 impl PartialOrd for LoxValue {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         match (self, other) {
-            (Self::Number(l), Self::Number(r)) => l.partial_cmp(r),
+            (Self::Int(l), Self::Int(r)) => l.partial_cmp(r),
+            (Self::Float(l), Self::Float(r)) => l.partial_cmp(r),
+            (Self::Int(l), Self::Float(r)) => (*l as f64).partial_cmp(r),
+            (Self::Float(l), Self::Int(r)) => l.partial_cmp(&(*r as f64)),
             (Self::String(l), Self::String(r)) => l.partial_cmp(r),
             _ => None,
         }
     }
 }
 
+impl LoxValue {
+    /// Writes the value, printing any list or map already being written
+    /// further up in `seen` as `[...]` or `{...}` rather than recursing
+    /// forever.
+    fn write(&self, f: &mut std::fmt::Formatter<'_>, seen: &mut Vec<*const ()>) -> std::fmt::Result {
+        match self {
+            Self::List(list) => {
+                let ptr = Rc::as_ptr(list) as *const ();
+                if seen.contains(&ptr) {
+                    return write!(f, "[...]");
+                }
+                seen.push(ptr);
+                write!(f, "[")?;
+                for (i, item) in list.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    item.write(f, seen)?;
+                }
+                seen.pop();
+                write!(f, "]")
+            }
+            Self::Map(map) => {
+                let ptr = Rc::as_ptr(map) as *const ();
+                if seen.contains(&ptr) {
+                    return write!(f, "{{...}}");
+                }
+                seen.push(ptr);
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    key.write(f, seen)?;
+                    write!(f, ": ")?;
+                    value.write(f, seen)?;
+                }
+                seen.pop();
+                write!(f, "}}")
+            }
+            _ => write!(f, "{self}"),
+        }
+    }
+}
+
 impl Display for LoxValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Number(n) => write!(f, "{}", n),
+            Self::List(_) | Self::Map(_) => self.write(f, &mut Vec::default()),
+            Self::Int(n) => write!(f, "{}", n),
+            // Floats always show a fraction or exponent, to set them apart
+            // from ints.
+            Self::Float(n) => write!(f, "{:?}", n),
             Self::String(s) => write!(f, "{}", s),
             Self::Bool(b) => write!(f, "{}", b),
+            Self::Function(func) => match func.name() {
+                Some(name) => write!(f, "<fn {}>", name),
+                None => write!(f, "<fn>"),
+            },
+            Self::NativeFunction(func) => write!(f, "<native fn {}>", func.name()),
+            Self::Class(class) => write!(f, "<class {}>", class.name),
+            Self::Instance(instance) => write!(f, "<{} instance>", instance.borrow().class.name),
             Self::Nil => write!(f, "nil"),
         }
     }
@@ -93,9 +237,15 @@ impl Display for LoxValue {
 impl Display for LoxType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Number => write!(f, "number"),
+            Self::Int => write!(f, "int"),
+            Self::Float => write!(f, "float"),
             Self::String => write!(f, "string"),
             Self::Bool => write!(f, "bool"),
+            Self::Function => write!(f, "function"),
+            Self::Class => write!(f, "class"),
+            Self::Instance => write!(f, "instance"),
+            Self::List => write!(f, "list"),
+            Self::Map => write!(f, "map"),
             Self::Nil => write!(f, "nil"),
         }
     }
@@ -105,6 +255,9 @@ impl From<Token> for LoxValue {
     fn from(token: Token) -> Self {
         match token.token_type.clone() {
             TokenType::String
+            | TokenType::Interpolation
+            | TokenType::InterpolationMiddle
+            | TokenType::InterpolationEnd
             | TokenType::True
             | TokenType::False
             | TokenType::Integer
@@ -121,8 +274,51 @@ impl From<TokenLiteral> for LoxValue {
             TokenLiteral::Nil(_) => Self::Nil,
             TokenLiteral::Bool(b) => Self::Bool(b),
             TokenLiteral::String(s) => Self::String(s),
-            TokenLiteral::Float(f) => Self::Number(f64::from(f)),
-            TokenLiteral::Integer(i) => Self::Number(f64::from(i)),
+            TokenLiteral::Float(f) => Self::Float(f),
+            TokenLiteral::Integer(i) => Self::Int(i),
+        }
+    }
+}
+
+/// Converts `f` to the int of the same value, if there is one.
+pub(crate) fn float_to_int(f: f64) -> Option<i64> {
+    // `i64::MAX as f64` rounds up to 2^63, which is out of range.
+    let in_range = f >= i64::MIN as f64 && f < i64::MAX as f64;
+    match f.fract() == 0.0 && in_range {
+        true => Some(f as i64),
+        false => None,
+    }
+}
+
+/// Why a value could not be used as an index into a list.
+pub(crate) enum IndexError {
+    WrongType(LoxType),
+    Negative(i64),
+    OutOfRange(i64, usize),
+}
+
+impl Display for IndexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WrongType(t) => write!(f, "List index must be a number, found {t}"),
+            Self::Negative(i) => write!(f, "List index {i} is negative"),
+            Self::OutOfRange(i, len) => {
+                write!(f, "List index {i} is out of range for a list of length {len}")
+            }
         }
     }
 }
+
+/// Converts `index` into a position in a list of `len` items.
+pub(crate) fn list_index(index: &LoxValue, len: usize) -> Result<usize, IndexError> {
+    let LoxValue::Int(i) = index else {
+        return Err(IndexError::WrongType(index.lox_type()));
+    };
+    if *i < 0 {
+        return Err(IndexError::Negative(*i));
+    }
+    match usize::try_from(*i) {
+        Ok(i) if i < len => Ok(i),
+        _ => Err(IndexError::OutOfRange(*i, len)),
+    }
+}
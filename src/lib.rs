@@ -0,0 +1,25 @@
+//! A tree-walking interpreter for the Lox language.
+//!
+//! ```
+//! # fn main() -> Result<(), Vec<rlox::Error>> {
+//! let mut lox = rlox::Lox::new();
+//! lox.set_global("name", rlox::LoxValue::String("world".to_string()));
+//! lox.eval(r#"var greeting = "hello " + name;"#)?;
+//! assert_eq!(
+//!     lox.get_global("greeting"),
+//!     Some(rlox::LoxValue::String("hello world".to_string()))
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+#![allow(clippy::result_large_err)]
+
+mod interpreter;
+mod lox;
+mod prelude;
+
+pub use interpreter::{Arity, Diagnostic, Dialect, Error, Frame, Label, LoxType, LoxValue, NativeError, NativeResult, Span};
+pub use interpreter::format;
+pub use interpreter::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken, SyntaxTree, TokenType, Trivia};
+pub use lox::Lox;
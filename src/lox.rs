@@ -0,0 +1,104 @@
+use crate::interpreter::{Arity, Dialect, Error, LoxInterpreter, LoxValue, NativeFunction, NativeResult};
+
+/// An embeddable Lox interpreter.
+///
+/// State persists across calls to [`Lox::eval`], so globals declared by one
+/// snippet are visible to the next.
+///
+/// Scripts may nest up to 1000 calls, past which a call fails with a stack
+/// overflow error. Nesting that deep takes more native stack than a thread
+/// has by default in a debug build, so deeply recursive scripts are best run
+/// on a thread spawned with a larger stack.
+pub struct Lox {
+    interpreter: LoxInterpreter,
+}
+
+impl Default for Lox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lox {
+    pub fn new() -> Self {
+        Self {
+            interpreter: LoxInterpreter::new(),
+        }
+    }
+
+    /// Creates an interpreter for a script invoked with `args`.
+    pub fn with_args(args: Vec<String>) -> Self {
+        Self {
+            interpreter: LoxInterpreter::with_args(args),
+        }
+    }
+
+    /// Arguments the interpreter was created with.
+    pub fn args(&self) -> &[String] {
+        self.interpreter.args()
+    }
+
+    /// Chooses how values other than bools behave in conditions and logical
+    /// operators, and how values of different types compare.
+    ///
+    /// ```
+    /// # use rlox::{Dialect, Lox, LoxValue};
+    /// let mut lox = Lox::new();
+    /// lox.set_dialect(Dialect::Canonical);
+    /// assert_eq!(lox.eval(r#"nil or "default";"#).unwrap(), LoxValue::String("default".to_string()));
+    /// assert_eq!(lox.eval(r#"1 == "1";"#).unwrap(), LoxValue::Bool(false));
+    /// ```
+    pub fn set_dialect(&mut self, dialect: Dialect) {
+        self.interpreter.set_dialect(dialect);
+    }
+
+    /// Evaluates `source`, returning the value of its last declaration.
+    ///
+    /// Nothing is evaluated if `source` fails to tokenize or parse, in which
+    /// case every error found is returned. Otherwise evaluation stops at the
+    /// first runtime error.
+    pub fn eval(&mut self, source: &str) -> Result<LoxValue, Vec<Error>> {
+        self.eval_file("<eval>", source)
+    }
+
+    /// Evaluates `source` like [`Lox::eval`], naming `file` as where it was
+    /// read from in the spans of any errors.
+    ///
+    /// ```
+    /// # use rlox::Lox;
+    /// let errs = Lox::new().eval_file("greet.lox", "print greeting;").unwrap_err();
+    /// let span = errs[0].span().unwrap();
+    /// assert_eq!((&*span.file, span.line, span.column), ("greet.lox", 1, 7));
+    /// ```
+    pub fn eval_file(&mut self, file: &str, source: &str) -> Result<LoxValue, Vec<Error>> {
+        self.interpreter.run(source.to_string(), file)
+    }
+
+    /// Defines, or redefines, the global variable `name`.
+    pub fn set_global(&mut self, name: &str, value: LoxValue) {
+        self.interpreter.define_global(name.to_string(), value);
+    }
+
+    /// Defines the global function `name`, implemented by `function`.
+    ///
+    /// ```
+    /// # use rlox::{Arity, Lox, LoxValue};
+    /// let mut lox = Lox::new();
+    /// lox.define_native("double", Arity::Fixed(1), |args| match &args[0] {
+    ///     LoxValue::Int(n) => Ok(LoxValue::Int(n * 2)),
+    ///     _ => Ok(LoxValue::Nil),
+    /// });
+    /// assert_eq!(lox.eval("double(21);").unwrap(), LoxValue::Int(42));
+    /// ```
+    pub fn define_native<F>(&mut self, name: &str, arity: Arity, function: F)
+    where
+        F: Fn(&[LoxValue]) -> NativeResult + 'static,
+    {
+        self.interpreter
+            .define_native(NativeFunction::new(name, arity, function));
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<LoxValue> {
+        self.interpreter.global(name)
+    }
+}
@@ -1,10 +1,22 @@
-mod interpreter;
-mod prelude;
+mod cli;
 
-use interpreter::*;
+use std::panic;
+use std::process::ExitCode;
+use std::thread;
 
-fn main() -> std::io::Result<()> {
-  let mut lox = LoxInterpreter::new();
-  
-  lox.launch()
+use cli::Command;
+
+/// The native stack scripts run with, deep enough for the interpreter to
+/// report a stack overflow before overflowing it, even in a debug build.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+fn main() -> ExitCode {
+  let main = thread::Builder::new()
+    .stack_size(STACK_SIZE)
+    .spawn(|| match Command::parse(std::env::args().skip(1)) {
+      Ok((command, options)) => command.execute(&options),
+      Err(message) => cli::usage_error(message),
+    })
+    .expect("failed to spawn the interpreter thread");
+  main.join().unwrap_or_else(|e| panic::resume_unwind(e))
 }
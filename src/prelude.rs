@@ -1,8 +1,7 @@
-pub use std::convert::{From, Into, AsRef};
+pub use std::convert::From;
 pub use std::default::Default;
-pub use std::fmt::{Debug as DebugTrait, Display};
+pub use std::fmt::Display;
 pub use std::iter::Peekable;
 pub use std::result::Result;
 pub use std::str::Chars;
-pub use std::sync::Arc;
 pub use std::vec::Vec;
\ No newline at end of file
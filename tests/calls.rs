@@ -0,0 +1,90 @@
+mod common;
+
+use std::thread;
+
+use common::{error, eval, printed};
+use rlox::{Lox, LoxValue};
+
+/// Runs `test` on a thread with a stack deep enough for the interpreter's
+/// limit on nested calls, as the command line runs scripts.
+fn with_deep_stack(test: fn()) {
+    thread::Builder::new()
+        .stack_size(256 * 1024 * 1024)
+        .spawn(test)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+#[test]
+fn unbounded_recursion_is_a_stack_overflow() {
+    with_deep_stack(|| {
+        let errs = Lox::new().eval("fun f(n) { return f(n + 1); } f(0);").unwrap_err();
+        assert_eq!(errs.len(), 1);
+        assert!(errs[0].is_runtime());
+        assert_eq!(errs[0].diagnostic().message, "Stack overflow");
+        let backtrace = errs[0].backtrace().unwrap();
+        assert_eq!(backtrace.last().unwrap().name, "<script>");
+        assert!(backtrace[..backtrace.len() - 1].iter().all(|frame| frame.name == "f"));
+    });
+}
+
+#[test]
+fn recursion_within_the_limit_returns() {
+    with_deep_stack(|| {
+        let source = "fun count(n) { if (n == 0) return 0; return 1 + count(n - 1); } count(500);";
+        assert_eq!(Lox::new().eval(source).unwrap(), LoxValue::Int(500));
+    });
+}
+
+#[test]
+fn the_interpreter_is_usable_after_a_stack_overflow() {
+    with_deep_stack(|| {
+        let mut lox = Lox::new();
+        lox.eval("fun f() { f(); }").unwrap();
+        assert!(lox.eval("f();").is_err());
+        assert_eq!(lox.eval("fun g(n) { return n; } g(1);").unwrap(), LoxValue::Int(1));
+    });
+}
+
+#[test]
+fn closures_keep_their_own_state() {
+    let source = "
+        fun makeCounter() {
+            var i = 0;
+            fun count() {
+                i = i + 1;
+                return i;
+            }
+            return count;
+        }
+        var a = makeCounter();
+        var b = makeCounter();
+        a();
+        a();
+        [a(), b()];
+    ";
+    assert_eq!(printed(source), "[3, 1]");
+}
+
+#[test]
+fn lambdas_are_values() {
+    assert_eq!(eval("var twice = fn(f, x) { return f(f(x)); }; twice(fn(n) { return n * 3; }, 2);"), LoxValue::Int(18));
+    assert_eq!(eval("fun id(x) { return x; } var f = id; f(7);"), LoxValue::Int(7));
+}
+
+#[test]
+fn a_function_without_a_return_value_returns_nil() {
+    assert_eq!(eval("fun f() { return; } f();"), LoxValue::Nil);
+    assert_eq!(eval("fun g() {} g();"), LoxValue::Nil);
+}
+
+#[test]
+fn calls_check_the_number_of_arguments() {
+    assert_eq!(error("fun f(a, b) {} f(1);"), ("E0012", "Expected 2 arguments but got 1".to_string()));
+}
+
+#[test]
+fn only_functions_and_classes_can_be_called() {
+    assert_eq!(error(r#""not a function"();"#), ("E0009", "Expected type function, found string".to_string()));
+}
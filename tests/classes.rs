@@ -0,0 +1,75 @@
+mod common;
+
+use common::{error, eval, printed};
+use rlox::LoxValue;
+
+#[test]
+fn initializers_set_fields_read_by_methods() {
+    let source = r#"
+        class Greeter {
+            init(name) { this.name = name; }
+            greet() { return "hello " + this.name; }
+        }
+        Greeter("ada").greet();
+    "#;
+    assert_eq!(printed(source), "hello ada");
+}
+
+#[test]
+fn fields_can_be_added_and_updated_from_outside() {
+    assert_eq!(eval("class A {} var a = A(); a.n = 1; a.n += 2; a.n;"), LoxValue::Int(3));
+}
+
+#[test]
+fn methods_stay_bound_to_their_instance() {
+    assert_eq!(eval("class A { m() { return this; } } var a = A(); var m = a.m; m() == a;"), LoxValue::Bool(true));
+}
+
+#[test]
+fn subclasses_inherit_and_override_methods() {
+    let source = "
+        class A {
+            init(x) { this.x = x; }
+            get() { return this.x; }
+            name() { return \"A\"; }
+        }
+        class B < A {
+            get() { return super.get() * 2; }
+        }
+        var b = B(21);
+        [b.get(), b.name()];
+    ";
+    assert_eq!(printed(source), "[42, A]");
+}
+
+#[test]
+fn calling_an_initializer_again_returns_the_instance() {
+    assert_eq!(eval("class A { init() { this.n = 1; } } var a = A(); a.n = 5; a.init().n;"), LoxValue::Int(1));
+}
+
+#[test]
+fn classes_and_instances_print_their_names() {
+    assert_eq!(printed("class A {} A;"), "<class A>");
+    assert_eq!(printed("class A {} A();"), "<A instance>");
+}
+
+#[test]
+fn an_undefined_property_is_a_runtime_error() {
+    assert_eq!(error("class A {} A().x;"), ("E0012", "Undefined property: x".to_string()));
+}
+
+#[test]
+fn class_declarations_are_checked_before_running() {
+    assert_eq!(error("print this;").0, "E0019");
+    assert_eq!(error("class A { m() { return super.m(); } }").0, "E0020");
+    assert_eq!(error("class A < A {}"), ("E0021", "Class A cannot inherit from itself".to_string()));
+    assert_eq!(error("class A { init() { return 1; } }").0, "E0018");
+}
+
+#[test]
+fn only_a_class_can_be_inherited_from() {
+    assert_eq!(
+        error("var NotAClass = 1; class B < NotAClass {}"),
+        ("E0009", "Expected type class, found int".to_string())
+    );
+}
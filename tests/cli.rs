@@ -0,0 +1,95 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Runs `rlox` with `args`, writing `stdin` to it.
+fn rlox(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+fn stderr(output: &Output) -> &str {
+    std::str::from_utf8(&output.stderr).unwrap()
+}
+
+#[test]
+fn evaluates_a_snippet() {
+    let output = rlox(&["-e", "print 1 + 2;"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "3\n");
+}
+
+#[test]
+fn runs_an_example_script() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/hello_world.lox");
+    let output = rlox(&[path], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(!stdout(&output).is_empty());
+}
+
+#[test]
+fn reads_a_script_from_stdin_and_passes_it_arguments() {
+    let output = rlox(&["-", "a", "b"], "print args();");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "[a, b]\n");
+}
+
+#[test]
+fn exits_with_65_on_syntax_errors_and_70_on_runtime_errors() {
+    assert_eq!(rlox(&["-e", "print 1 +;"], "").status.code(), Some(65));
+    assert_eq!(rlox(&["-e", "print nil + 1;"], "").status.code(), Some(70));
+}
+
+#[test]
+fn exits_with_the_status_a_script_asks_for() {
+    assert_eq!(rlox(&["-e", "exit(3);"], "").status.code(), Some(3));
+}
+
+#[test]
+fn reports_usage_and_missing_scripts() {
+    let output = rlox(&["--bogus"], "");
+    assert_eq!(output.status.code(), Some(64));
+    assert!(stderr(&output).contains("Usage: rlox"));
+    assert_eq!(rlox(&["/nonexistent/script.lox"], "").status.code(), Some(66));
+}
+
+#[test]
+fn reports_deep_recursion_as_a_runtime_error() {
+    let output = rlox(&["-e", "fun f(n) { return f(n + 1); } f(0);"], "");
+    assert_eq!(output.status.code(), Some(70));
+    assert!(stderr(&output).starts_with("error[E0012]: Stack overflow"));
+    assert!(stderr(&output).contains("frames omitted"));
+}
+
+#[test]
+fn writes_errors_as_json_lines() {
+    let output = rlox(&["--error-format", "json", "-e", "var a = @; print 1 +;"], "");
+    assert_eq!(output.status.code(), Some(65));
+    let lines = stderr(&output).lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with(r#"{"code":"E0001","#));
+    assert!(lines[1].starts_with(r#"{"code":"E0006","#));
+}
+
+#[test]
+fn formats_stdin_to_stdout() {
+    let output = rlox(&["fmt", "-"], "var x=1;");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "var x = 1;\n");
+}
+
+#[test]
+fn checks_whether_source_is_formatted() {
+    assert_eq!(rlox(&["fmt", "--check", "-"], "var x = 1;\n").status.code(), Some(0));
+    assert_eq!(rlox(&["fmt", "--check", "-"], "var x=1;").status.code(), Some(1));
+}
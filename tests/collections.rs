@@ -0,0 +1,62 @@
+mod common;
+
+use common::{error, eval, printed};
+use rlox::LoxValue;
+
+#[test]
+fn lists_can_be_indexed_and_updated() {
+    assert_eq!(printed("var l = [1, 2, 3,]; l[0] = 10; l[2] += 1; push(l, 5); l;"), "[10, 2, 4, 5]");
+    assert_eq!(eval("[[1, 2], [3]][0][1];"), LoxValue::Int(2));
+}
+
+#[test]
+fn lists_are_shared_by_reference() {
+    assert_eq!(printed("var l = [1]; var m = l; push(m, 2); l;"), "[1, 2]");
+}
+
+#[test]
+fn list_natives_insert_and_remove_items() {
+    assert_eq!(printed("var l = [1, 3]; insert(l, 1, 2); insert(l, 3, 4); l;"), "[1, 2, 3, 4]");
+    assert_eq!(printed("var l = [1, 2, 3]; [remove(l, 0), pop(l), l];"), "[1, 3, [2]]");
+}
+
+#[test]
+fn list_indices_are_checked() {
+    assert_eq!(error("[1][5];"), ("E0012", "List index 5 is out of range for a list of length 1".to_string()));
+    assert_eq!(error("[1][-1];"), ("E0012", "List index -1 is negative".to_string()));
+    assert_eq!(error("[0, 1][1.0];"), ("E0009", "Expected type int, found float".to_string()));
+    assert_eq!(error("pop([]);"), ("E0012", "Cannot pop from an empty list".to_string()));
+}
+
+#[test]
+fn maps_can_be_read_and_updated() {
+    let source = r#"var m = {"a": 1, "b": 2,}; m["a"] += 1; m["c"] = 3; m;"#;
+    assert_eq!(printed(source), "{a: 2, b: 2, c: 3}");
+    assert_eq!(printed(r#"var m = {"a": 1, "b": 2}; remove(m, "a"); [keys(m), values(m), len(m)];"#), "[[b], [2], 1]");
+}
+
+#[test]
+fn equal_numbers_are_the_same_key() {
+    assert_eq!(printed(r#"var m = {1: "a", 1.0: "b"}; m;"#), "{1: b}");
+    assert_eq!(eval(r#"len({1: "a", 1.0: "b"});"#), LoxValue::Int(1));
+    assert_eq!(printed("var m = {-0.0: 1, 0: 2}; m;"), "{0.0: 2}");
+    assert_eq!(eval("has({1: true}, 1.0);"), LoxValue::Bool(true));
+}
+
+#[test]
+fn keys_of_different_types_are_different_keys() {
+    assert_eq!(eval(r#"has({"1": 1}, 1);"#), LoxValue::Bool(false));
+    assert_eq!(eval(r#"len({nil: 1, false: 2, 0: 3, "": 4});"#), LoxValue::Int(4));
+}
+
+#[test]
+fn only_immutable_values_can_be_keys() {
+    let message = "Map keys must be nil, bool, number or string, found list".to_string();
+    assert_eq!(error("var m = {[1]: 2};"), ("E0012", message.clone()));
+    assert_eq!(error("var m = {}; m[[1]] = 1;"), ("E0012", message));
+}
+
+#[test]
+fn a_missing_key_is_a_runtime_error() {
+    assert_eq!(error(r#"var m = {"a": 1}; m["b"];"#), ("E0012", "Key b is not in the map".to_string()));
+}
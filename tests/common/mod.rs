@@ -0,0 +1,36 @@
+//! Helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use rlox::{Lox, LoxValue};
+
+/// Evaluates `source`, which must run without errors, returning the value of
+/// its last declaration.
+pub fn eval(source: &str) -> LoxValue {
+    match Lox::new().eval(source) {
+        Ok(value) => value,
+        Err(errs) => panic!("{source:?} failed: {errs:?}"),
+    }
+}
+
+/// Evaluates `source` like [`eval`], returning the value as Lox prints it.
+pub fn printed(source: &str) -> String {
+    eval(source).to_string()
+}
+
+/// The code and message of each error found evaluating `source`.
+pub fn errors(source: &str) -> Vec<(&'static str, String)> {
+    Lox::new()
+        .eval(source)
+        .unwrap_err()
+        .iter()
+        .map(|e| (e.code(), e.diagnostic().message))
+        .collect()
+}
+
+/// The code and message of the single error found evaluating `source`.
+pub fn error(source: &str) -> (&'static str, String) {
+    let mut errors = errors(source);
+    assert_eq!(errors.len(), 1, "{source:?} failed with {errors:?}");
+    errors.remove(0)
+}
@@ -0,0 +1,95 @@
+use rlox::{Error, Lox};
+
+fn errors(file: &str, source: &str) -> Vec<Error> {
+    Lox::new().eval_file(file, source).unwrap_err()
+}
+
+#[test]
+fn spans_point_at_the_offending_characters() {
+    let errs = errors("t.lox", "var s = 1;\ns - \"a\";");
+    let span = errs[0].span().unwrap();
+    assert_eq!((&*span.file, span.start, span.end), ("t.lox", 13, 14));
+    assert_eq!((span.line, span.column, span.end_line, span.end_column), (2, 3, 2, 4));
+}
+
+#[test]
+fn columns_count_characters_and_offsets_count_bytes() {
+    let errs = errors("t.lox", "\"é\" - 1;");
+    let span = errs[0].span().unwrap();
+    assert_eq!((span.start, span.column), (5, 5));
+}
+
+#[test]
+fn errors_render_with_the_source_they_point_at() {
+    let source = "var a = 1;\nprint a + nil;";
+    let errs = errors("t.lox", source);
+    assert_eq!(
+        errs[0].diagnostic().render("t.lox", source, false),
+        "\
+error[E0010]: Incompatible types for `+`: int and nil
+ --> t.lox:2:9
+  |
+2 | print a + nil;
+  |         ^ operator cannot combine int and nil
+  |       - left operand is int
+  |           --- right operand is nil
+"
+    );
+}
+
+#[test]
+fn errors_render_as_one_line_of_json() {
+    let errs = errors("t.lox", "print nope;");
+    let json = errs[0].diagnostic().to_json();
+    assert!(!json.contains('\n'));
+    assert!(json.starts_with(r#"{"code":"E0013","severity":"error","message":"Undefined variable nope","#));
+    assert!(json.contains(r#""file":"t.lox","#));
+    assert!(json.contains(r#""line":1,"column":7,"end_line":1,"end_column":11"#));
+}
+
+#[test]
+fn every_error_has_a_stable_code() {
+    let errs = errors("t.lox", "var a = @; print 1 +;");
+    let codes = errs.iter().map(Error::code).collect::<Vec<_>>();
+    assert_eq!(codes, ["E0001", "E0006"]);
+}
+
+#[test]
+fn undefined_names_suggest_similar_names_in_scope() {
+    let errs = errors("t.lox", "var apple = 1;\nprint aple;");
+    assert_eq!(errs[0].diagnostic().help.as_deref(), Some("did you mean `apple`?"));
+    let errs = errors("t.lox", "fun f() { var local = 1; return locl; } f();");
+    assert_eq!(errs[0].diagnostic().help.as_deref(), Some("did you mean `local`?"));
+}
+
+#[test]
+fn misspelled_keywords_are_suggested() {
+    let errs = errors("t.lox", "var x = 1;\npritn x;");
+    assert_eq!(errs[0].diagnostic().help.as_deref(), Some("did you mean `print`?"));
+}
+
+#[test]
+fn names_too_different_are_not_suggested() {
+    let errs = errors("t.lox", "print xyzzy;");
+    assert_eq!(errs[0].diagnostic().help, None);
+}
+
+#[test]
+fn runtime_errors_carry_a_backtrace_innermost_first() {
+    let source = "fun f() { g(); }\nfun g() { nil + 1; }\nf();";
+    let errs = errors("t.lox", source);
+    let frames = errs[0]
+        .backtrace()
+        .unwrap()
+        .iter()
+        .map(|frame| (frame.name.as_str(), frame.span.line, frame.span.column))
+        .collect::<Vec<_>>();
+    assert_eq!(frames, [("g", 2, 15), ("f", 1, 11), ("<script>", 3, 1)]);
+}
+
+#[test]
+fn syntax_errors_have_no_backtrace() {
+    let errs = errors("t.lox", "print 1 +;");
+    assert!(errs[0].backtrace().is_none());
+    assert!(!errs[0].is_runtime());
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::{error, eval};
+use rlox::{Dialect, Lox, LoxValue};
+
+fn canonical(source: &str) -> LoxValue {
+    let mut lox = Lox::new();
+    lox.set_dialect(Dialect::Canonical);
+    lox.eval(source).unwrap()
+}
+
+#[test]
+fn logical_operators_short_circuit() {
+    let source = "var calls = 0; fun f() { calls += 1; return true; } false and f(); true or f(); calls;";
+    assert_eq!(eval(source), LoxValue::Int(0));
+    assert_eq!(canonical(source), LoxValue::Int(0));
+}
+
+#[test]
+fn strict_conditions_must_be_bools() {
+    assert_eq!(error("if (1) print 1;").0, "E0012");
+    assert_eq!(error(r#"nil or "x";"#), ("E0009", "Expected type bool, found nil".to_string()));
+    assert_eq!(error(r#"1 == "1";"#), ("E0010", "Incompatible types for `==`: int and string".to_string()));
+    assert_eq!(eval("1 == nil;"), LoxValue::Bool(false));
+}
+
+#[test]
+fn canonical_values_other_than_nil_and_false_are_truthy() {
+    assert_eq!(canonical(r#"nil or "default";"#), LoxValue::String("default".to_string()));
+    assert_eq!(canonical(r#"0 and "zero";"#), LoxValue::String("zero".to_string()));
+    assert_eq!(canonical(r#""" ? 1 : 2;"#), LoxValue::Int(1));
+    assert_eq!(canonical("!nil;"), LoxValue::Bool(true));
+}
+
+#[test]
+fn canonical_values_of_different_types_are_unequal() {
+    assert_eq!(canonical(r#"1 == "1";"#), LoxValue::Bool(false));
+    assert_eq!(canonical("1 == 1.0;"), LoxValue::Bool(true));
+}
@@ -0,0 +1,45 @@
+use rlox::{Arity, Lox, LoxValue, NativeError};
+
+#[test]
+fn globals_persist_across_evaluations() {
+    let mut lox = Lox::new();
+    lox.eval("var count = 1;").unwrap();
+    lox.eval("count = count + 1;").unwrap();
+    assert_eq!(lox.get_global("count"), Some(LoxValue::Int(2)));
+}
+
+#[test]
+fn the_embedder_can_set_globals() {
+    let mut lox = Lox::new();
+    lox.set_global("name", LoxValue::String("world".to_string()));
+    assert_eq!(lox.eval(r#""hello " + name;"#).unwrap(), LoxValue::String("hello world".to_string()));
+}
+
+#[test]
+fn native_functions_are_called_with_checked_arguments() {
+    let mut lox = Lox::new();
+    lox.define_native("double", Arity::Fixed(1), |args| match &args[0] {
+        LoxValue::Int(n) => Ok(LoxValue::Int(n * 2)),
+        value => Err(NativeError::WrongType(0, value.lox_type(), vec![rlox::LoxType::Int])),
+    });
+    assert_eq!(lox.eval("double(21);").unwrap(), LoxValue::Int(42));
+    assert_eq!(lox.eval("double(1, 2);").unwrap_err()[0].code(), "E0012");
+    let errs = lox.eval(r#"double("a");"#).unwrap_err();
+    assert_eq!(errs[0].diagnostic().message, "Expected type int, found string");
+}
+
+#[test]
+fn a_native_function_can_fail() {
+    let mut lox = Lox::new();
+    lox.define_native("fail", Arity::Fixed(0), |_| Err(NativeError::Failed("no luck".to_string())));
+    let errs = lox.eval("fail();").unwrap_err();
+    assert_eq!(errs[0].diagnostic().message, "no luck");
+    assert_eq!(errs[0].backtrace().unwrap()[0].name, "fail");
+}
+
+#[test]
+fn scripts_see_their_arguments() {
+    let mut lox = Lox::with_args(vec!["a".to_string(), "b".to_string()]);
+    assert_eq!(lox.eval("args();").unwrap().to_string(), "[a, b]");
+    assert_eq!(lox.args(), ["a", "b"]);
+}
@@ -0,0 +1,92 @@
+use std::fs;
+use std::path::Path;
+
+use rlox::SyntaxTree;
+
+fn format(source: &str) -> String {
+    rlox::format(source, "<test>").unwrap()
+}
+
+/// The source of every example script, along with its path.
+fn examples() -> Vec<(String, String)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let mut examples = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lox"))
+        .map(|path| (path.display().to_string(), fs::read_to_string(&path).unwrap()))
+        .collect::<Vec<_>>();
+    examples.sort();
+    examples
+}
+
+#[test]
+fn the_syntax_tree_reproduces_its_source() {
+    for (path, source) in examples() {
+        assert_eq!(SyntaxTree::parse(&source, &path).root().to_string(), source, "{path}");
+    }
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    for (path, source) in examples() {
+        let formatted = rlox::format(&source, &path).unwrap();
+        assert_eq!(format(&formatted), formatted, "{path}");
+    }
+}
+
+#[test]
+fn spacing_is_normalized() {
+    assert_eq!(
+        format("var   x=1+2 ;\nfor(var i=0;i<3;i=i+1)print i;"),
+        "var x = 1 + 2;\nfor (var i = 0; i < 3; i = i + 1) print i;\n"
+    );
+}
+
+#[test]
+fn blocks_are_broken_and_indented() {
+    assert_eq!(
+        format("class A<B{m(){if(x){print 1;}else{print 2;}}}"),
+        "class A < B {\n  m() {\n    if (x) {\n      print 1;\n    } else {\n      print 2;\n    }\n  }\n}\n"
+    );
+}
+
+#[test]
+fn comments_and_single_blank_lines_are_kept() {
+    assert_eq!(
+        format("var a = 1;\n\n\n// lead\nprint a; // trail\n/* block */ print a;"),
+        "var a = 1;\n\n// lead\nprint a; // trail\n/* block */ print a;\n"
+    );
+}
+
+#[test]
+fn long_lists_are_broken_one_item_per_line_with_a_trailing_comma() {
+    let args = ["a".repeat(20), "b".repeat(30), "c".repeat(24), "d".repeat(18)];
+    assert_eq!(
+        format(&format!("print f({});", args.join(", "))),
+        format!("print f(\n{});\n", args.map(|arg| format!("  {arg},\n")).concat())
+    );
+    assert_eq!(format("var l = [1, 2, 3,];"), "var l = [1, 2, 3];\n");
+}
+
+#[test]
+fn source_with_syntax_errors_is_not_formatted() {
+    let errs = rlox::format("print 1 +;", "<test>").unwrap_err();
+    assert_eq!(errs[0].code(), "E0006");
+}
+
+#[test]
+fn nested_minuses_are_kept_apart() {
+    assert_eq!(format("print - -1;\nprint - - -x;\n"), "print - -1;\nprint - - -x;\n");
+    assert_eq!(format("print -(-1);\nprint -~-1;\nprint !!x;\n"), "print -(-1);\nprint -~-1;\nprint !!x;\n");
+}
+
+#[test]
+fn a_body_after_a_line_comment_is_indented() {
+    assert_eq!(format("if (x) // c\nprint 1;\n"), "if (x) // c\n  print 1;\n");
+    assert_eq!(format("while (x) // c\nprint 1;\n"), "while (x) // c\n  print 1;\n");
+    assert_eq!(
+        format("{\nif (a) print 1; else // c\nprint 2;\n}\n"),
+        "{\n  if (a) print 1;\n  else // c\n    print 2;\n}\n"
+    );
+}
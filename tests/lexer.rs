@@ -0,0 +1,66 @@
+mod common;
+
+use common::{error, printed};
+
+#[test]
+fn escape_sequences_are_translated() {
+    assert_eq!(printed(r#""a\tb\nc";"#), "a\tb\nc");
+    assert_eq!(printed(r#""\\ \" \' \$ \0";"#), "\\ \" ' $ \0");
+    assert_eq!(printed(r#""\u{41}\u{1F600}";"#), "A\u{1F600}");
+}
+
+#[test]
+fn invalid_escape_sequences_are_reported() {
+    assert_eq!(error(r#""\q";"#), ("E0004", r"Invalid escape sequence \q".to_string()));
+    assert_eq!(error(r#""\u{110000}";"#), ("E0004", r"Invalid escape sequence \u{110000}".to_string()));
+}
+
+#[test]
+fn raw_strings_have_no_escapes() {
+    assert_eq!(printed(r#"r"\d+\n";"#), r"\d+\n");
+    assert_eq!(printed(r###"r#"a "quoted" word"#;"###), r#"a "quoted" word"#);
+}
+
+#[test]
+fn triple_quoted_strings_span_lines_and_hold_quotes() {
+    assert_eq!(printed("\"\"\"first\nsay \"hi\" now\"\"\";"), "first\nsay \"hi\" now");
+}
+
+#[test]
+fn interpolated_expressions_are_evaluated_and_converted() {
+    assert_eq!(printed(r#""a ${1 + 2} b ${[1, nil]}";"#), "a 3 b [1, nil]");
+    assert_eq!(printed(r#"var name = "lox"; "hello ${name}!";"#), "hello lox!");
+}
+
+#[test]
+fn interpolations_nest_and_hold_braces() {
+    assert_eq!(printed(r#""nested ${ {"k": "v ${1 + 1}"}["k"] } end";"#), "nested v 2 end");
+    assert_eq!(printed(r#""${"}"}";"#), "}");
+    assert_eq!(printed(r#""raw ${r"\d"}";"#), r"raw \d");
+}
+
+#[test]
+fn an_escaped_dollar_is_not_an_interpolation() {
+    assert_eq!(printed(r#""\${1}";"#), "${1}");
+}
+
+#[test]
+fn block_comments_nest() {
+    assert_eq!(printed("/* a /* nested */ comment */ 1;"), "1");
+    assert_eq!(printed("/** a doc comment */\n/// another\n1;"), "1");
+    assert_eq!(error("/* /* */ 1;").0, "E0003");
+}
+
+#[test]
+fn numbers_can_be_written_in_other_bases_and_with_separators() {
+    assert_eq!(printed("[0x1F, 0b101, 0o17, 1_000, 0xff_ff];"), "[31, 5, 15, 1000, 65535]");
+    assert_eq!(printed("[1e3, 1.5e-3, 2E+2];"), "[1000.0, 0.0015, 200.0]");
+}
+
+#[test]
+fn malformed_numbers_are_reported() {
+    assert_eq!(error("1__0;"), ("E0005", "Invalid number literal 1__0: misplaced digit separator".to_string()));
+    assert_eq!(error("0x;"), ("E0005", "Invalid number literal 0x: missing digits".to_string()));
+    assert_eq!(error("1e;"), ("E0005", "Invalid number literal 1e: missing exponent digits".to_string()));
+    assert_eq!(error("0b102;").0, "E0005");
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use common::{error, eval, printed};
+use rlox::LoxValue;
+
+#[test]
+fn while_loops_run_until_their_condition_is_false() {
+    assert_eq!(eval("var i = 0; while (i < 5) i = i + 1; i;"), LoxValue::Int(5));
+}
+
+#[test]
+fn for_loops_run_each_clause() {
+    assert_eq!(printed("var seen = []; for (var i = 0; i < 3; i = i + 1) push(seen, i); seen;"), "[0, 1, 2]");
+}
+
+#[test]
+fn any_clause_of_a_for_loop_may_be_left_out() {
+    assert_eq!(eval("var i = 0; for (;;) { i = i + 1; if (i == 4) break; } i;"), LoxValue::Int(4));
+}
+
+#[test]
+fn continue_skips_to_the_next_iteration() {
+    let source = "var seen = []; for (var i = 0; i < 4; i = i + 1) { if (i == 1) continue; push(seen, i); } seen;";
+    assert_eq!(printed(source), "[0, 2, 3]");
+}
+
+#[test]
+fn break_leaves_only_the_innermost_loop() {
+    let source = "
+        var seen = [];
+        for (var i = 0; i < 2; i = i + 1) {
+            while (true) {
+                push(seen, i);
+                break;
+            }
+        }
+        seen;
+    ";
+    assert_eq!(printed(source), "[0, 1]");
+}
+
+#[test]
+fn break_and_continue_are_only_allowed_in_loops() {
+    assert_eq!(error("break;"), ("E0014", "'break' outside of a loop".to_string()));
+    assert_eq!(error("while (true) { fun f() { continue; } }").0, "E0014");
+}
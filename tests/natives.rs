@@ -0,0 +1,73 @@
+mod common;
+
+use common::printed;
+use rlox::{Error, Lox, LoxType};
+
+fn error(source: &str) -> Error {
+    let mut errs = Lox::new().eval(source).unwrap_err();
+    assert_eq!(errs.len(), 1);
+    errs.remove(0)
+}
+
+/// The types `error` reports were expected in place of the one found.
+fn expected_types(error: &Error) -> Vec<LoxType> {
+    let error = match error {
+        Error::WithBacktrace(error, _) => error,
+        error => error,
+    };
+    match error {
+        Error::WrongType(_, _, _, expected) => expected.clone(),
+        error => panic!("expected a type error, found {error:?}"),
+    }
+}
+
+#[test]
+fn int_accepts_numbers() {
+    let error = error(r#"int("1");"#);
+    assert_eq!(expected_types(&error), [LoxType::Int, LoxType::Float]);
+    assert_eq!(error.diagnostic().message, "Expected type int or float, found string");
+}
+
+#[test]
+fn float_accepts_numbers() {
+    assert_eq!(expected_types(&error("float(nil);")), [LoxType::Int, LoxType::Float]);
+}
+
+#[test]
+fn len_accepts_strings_lists_and_maps() {
+    let error = error("len(1);");
+    assert_eq!(expected_types(&error), [LoxType::String, LoxType::List, LoxType::Map]);
+    assert_eq!(error.diagnostic().message, "Expected type string, list or map, found int");
+}
+
+#[test]
+fn conversions_return_values_of_the_target_type() {
+    let mut lox = Lox::new();
+    assert_eq!(lox.eval("int(-2.7);").unwrap(), rlox::LoxValue::Int(-2));
+    assert_eq!(lox.eval("float(2);").unwrap(), rlox::LoxValue::Float(2.0));
+    assert_eq!(lox.eval(r#"len("héllo");"#).unwrap(), rlox::LoxValue::Int(5));
+}
+
+#[test]
+fn values_convert_to_strings_and_numbers() {
+    assert_eq!(printed(r#"[str(1.5) + "x", num(" 42 "), num("1.5")];"#), "[1.5x, 42, 1.5]");
+    assert_eq!(common::error(r#"num("abc");"#), ("E0012", r#"Cannot convert "abc" to a number"#.to_string()));
+}
+
+#[test]
+fn typeof_names_the_type_of_a_value() {
+    assert_eq!(
+        printed(r#"[typeof(nil), typeof(true), typeof("a"), typeof([]), typeof({}), typeof(clock), typeof(fn() {})];"#),
+        "[nil, bool, string, list, map, function, function]"
+    );
+}
+
+#[test]
+fn exit_is_reported_to_the_embedder() {
+    let errs = Lox::new().eval("exit(3); print 1;").unwrap_err();
+    assert!(matches!(errs[..], [Error::Exit(3)]));
+    assert_eq!(
+        common::error("exit(256);"),
+        ("E0012", "Invalid exit status 256, expected 0 to 255".to_string())
+    );
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use common::{error, eval, printed};
+use rlox::LoxValue;
+
+#[test]
+fn ints_and_floats_are_distinct_types() {
+    assert_eq!(eval("1;"), LoxValue::Int(1));
+    assert_eq!(eval("1.0;"), LoxValue::Float(1.0));
+    assert_eq!(printed("[typeof(1), typeof(1.0)];"), "[int, float]");
+}
+
+#[test]
+fn integer_division_truncates() {
+    assert_eq!(printed("[10 / 4, -7 / 2, 7.0 / 2, -7 % 3];"), "[2, -3, 3.5, -1]");
+}
+
+#[test]
+fn mixed_arithmetic_is_done_in_floats() {
+    assert_eq!(eval("1 + 2.5;"), LoxValue::Float(3.5));
+    assert_eq!(printed("[3 == 3.0, 1 < 1.5];"), "[true, true]");
+}
+
+#[test]
+fn integer_overflow_and_division_by_zero_are_runtime_errors() {
+    assert_eq!(error("9223372036854775807 + 1;"), ("E0012", "Integer overflow".to_string()));
+    assert_eq!(error("1 / 0;"), ("E0012", "Divide by zero".to_string()));
+}
+
+#[test]
+fn exponents_and_bitwise_operators() {
+    assert_eq!(printed("[2 ** 10, 2 ** -1, 2.0 ** 2];"), "[1024, 0.5, 4.0]");
+    assert_eq!(printed("[5 & 3 | 8 ^ 1, ~0, 1 << 4, 1 >> 1, 6 ^ 3];"), "[9, -1, 16, 0, 5]");
+    assert_eq!(error("1 & 1.0;"), ("E0011", "Operator `&` cannot be used on int and float".to_string()));
+}
+
+#[test]
+fn the_ternary_operator_picks_a_branch() {
+    assert_eq!(eval("true ? 1 : 2;"), LoxValue::Int(1));
+    assert_eq!(eval("false ? 1 : true ? 2 : 3;"), LoxValue::Int(2));
+}
+
+#[test]
+fn compound_assignment_applies_the_operator() {
+    assert_eq!(eval("var x = 10; x -= 3; x *= 2; x /= 4; x %= 2; x;"), LoxValue::Int(1));
+    assert_eq!(printed(r#"var s = "a"; s += "b"; s;"#), "ab");
+}
+
+#[test]
+fn compound_assignment_evaluates_its_target_once() {
+    let source = "
+        var calls = 0;
+        fun slot() { calls += 1; return 1; }
+        var counts = [0, 0];
+        counts[slot()] += 3;
+        [counts, calls];
+    ";
+    assert_eq!(printed(source), "[[0, 3], 1]");
+}
+
+#[test]
+fn unary_minus_accepts_either_number_type() {
+    assert_eq!(error(r#"-"a";"#), ("E0009", "Expected type int or float, found string".to_string()));
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use common::{error, errors};
+use rlox::{Lox, SyntaxTree};
+
+/// The codes of the errors found evaluating `source`.
+fn codes(source: &str) -> Vec<&'static str> {
+    errors(source).into_iter().map(|(code, _)| code).collect()
+}
+
+#[test]
+fn every_syntax_error_is_reported_in_one_pass() {
+    assert_eq!(
+        errors("print 1 +; fun f( { print 1; } print 2 +; class { }"),
+        [
+            ("E0006", "Expected expression, found ;".to_string()),
+            ("E0006", "Expected parameter name, found {".to_string()),
+            ("E0006", "Expected expression, found ;".to_string()),
+            ("E0006", "Expected class name, found {".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn lexer_and_parser_errors_are_reported_together_in_source_order() {
+    assert_eq!(codes(r#"var a = @; var b = "\q"; print 1 +; a + b = 3;"#), ["E0001", "E0004", "E0006", "E0007"]);
+}
+
+#[test]
+fn an_error_does_not_hide_the_statements_after_it() {
+    assert_eq!(codes("print (1 + 2; print 3 +; (a) = 1;"), ["E0006", "E0006", "E0007"]);
+}
+
+#[test]
+fn nothing_runs_when_the_source_has_a_syntax_error() {
+    let mut lox = Lox::new();
+    assert!(lox.eval("var ran = true; print 1 +;").is_err());
+    assert_eq!(lox.get_global("ran"), None);
+}
+
+#[test]
+fn a_token_that_failed_to_tokenize_is_reported_once() {
+    assert_eq!(error("var a = @;"), ("E0001", "Unexpected token @".to_string()));
+    assert_eq!(codes(r#"print "abc; print 1 +;"#), ["E0002"]);
+    assert_eq!(codes("print 1 /* unterminated"), ["E0003"]);
+}
+
+#[test]
+fn the_syntax_tree_covers_source_with_errors() {
+    let source = "print 1 +; var = 3;\nprint 2;";
+    let tree = SyntaxTree::parse(source, "<test>");
+    assert_eq!(tree.errors().len(), 2);
+    assert_eq!(tree.root().to_string(), source);
+}
+
+#[test]
+fn an_open_interpolation_is_reported_once_at_the_end_of_a_map() {
+    assert_eq!(error(r#"print "a ${ {"#), ("E0002", "Unterminated string literal".to_string()));
+}
+
+#[test]
+fn an_open_interpolation_is_reported_once_at_the_end_of_an_operation() {
+    assert_eq!(error(r#"print "x ${1 + "#), ("E0002", "Unterminated string literal".to_string()));
+}
+
+#[test]
+fn an_open_interpolation_is_reported_once_at_the_end_of_a_call() {
+    assert_eq!(error(r#"print "a ${f("#), ("E0002", "Unterminated string literal".to_string()));
+}
+
+#[test]
+fn a_missing_token_at_the_end_of_input_is_named() {
+    assert_eq!(error("print (1"), ("E0006", "Expected ), found end of input".to_string()));
+}
+
+#[test]
+fn a_missing_operand_is_an_expected_expression() {
+    assert_eq!(error("var x = ;"), ("E0006", "Expected expression, found ;".to_string()));
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use common::{error, eval, printed};
+use rlox::LoxValue;
+
+#[test]
+fn nested_blocks_shadow_and_restore_variables() {
+    let source = r#"
+        var a = "global";
+        var seen = [];
+        {
+            var a = "outer";
+            {
+                var a = "inner";
+                push(seen, a);
+            }
+            push(seen, a);
+        }
+        push(seen, a);
+        seen;
+    "#;
+    assert_eq!(printed(source), "[inner, outer, global]");
+}
+
+#[test]
+fn assignment_updates_the_enclosing_variable() {
+    assert_eq!(eval("var a = 1; { { a = 2; } } a;"), LoxValue::Int(2));
+}
+
+#[test]
+fn closures_bind_the_variable_in_scope_where_they_are_declared() {
+    let source = r#"
+        var a = "global";
+        var seen = [];
+        {
+            fun show() { push(seen, a); }
+            show();
+            var a = "block";
+            show();
+        }
+        seen;
+    "#;
+    assert_eq!(printed(source), "[global, global]");
+}
+
+#[test]
+fn a_local_cannot_be_read_in_its_own_initializer() {
+    assert_eq!(
+        error("{ var a = a; }"),
+        ("E0015", "Cannot read local variable a in its own initializer".to_string())
+    );
+}
+
+#[test]
+fn a_local_cannot_be_declared_twice_in_one_scope() {
+    assert_eq!(
+        error("{ var a = 1; var a = 2; }"),
+        ("E0016", "Variable a is already declared in this scope".to_string())
+    );
+    // Globals may be redeclared, as in a REPL.
+    assert_eq!(eval("var a = 1; var a = 2; a;"), LoxValue::Int(2));
+}
+
+#[test]
+fn return_is_only_allowed_in_functions() {
+    assert_eq!(error("return 1;"), ("E0017", "Cannot return from top-level code".to_string()));
+}
+
+#[test]
+fn an_undefined_variable_is_a_runtime_error() {
+    let (code, message) = error("var x = 1; x = y + 1;");
+    assert_eq!((code, message.as_str()), ("E0013", "Undefined variable y"));
+}